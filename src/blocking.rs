@@ -0,0 +1,161 @@
+//! A synchronous API for code that doesn't run inside an async runtime.
+//!
+//! Requires the `blocking` feature.
+
+use crate::{remove_matching, replace_first, DbError};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A blocking Database runs the same operations as [`crate::Database`] on the calling thread,
+/// without an async runtime.
+///
+/// A blocking Database can also be created from a configured Database, with [`From`]. Its methods
+/// must not be called from within an async runtime, as they block the thread while waiting for
+/// the lock of a collection.
+///
+/// # Examples
+///
+/// ```
+/// use csv_db::blocking::Database;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// struct User {
+///     id: usize,
+///     first_name: String,
+///     last_name: String,
+///     age: u32,
+/// }
+///
+/// fn main() {
+/// #   let _ = std::fs::remove_dir_all("data/blocking");
+///     let db = Database::new("data/blocking", None);
+///
+///     for id in 1..=3 {
+///         let user = User {
+///             id,
+///             first_name: String::from("First"),
+///             last_name: String::from("Last"),
+///             age: 20,
+///         };
+///
+///         db.insert("users", user).expect("Problem inserting user.");
+///     }
+///
+///     let user = User {
+///         id: 2,
+///         first_name: String::from("Second"),
+///         last_name: String::from("Last"),
+///         age: 30,
+///     };
+///
+///     db.update("users", user, |u: &User| u.id == 2)
+///         .expect("Problem updating user.");
+///
+///     let deleted = db
+///         .delete("users", |u: &User| u.id == 1)
+///         .expect("Problem deleting user.");
+///
+///     assert_eq!(deleted, 1);
+///
+///     let users = db
+///         .find("users", |_: &User| true)
+///         .expect("Problem searching users.");
+///
+///     assert_eq!(users.len(), 2);
+///     assert_eq!(users[0].first_name, "Second");
+///     assert_eq!(users[1].id, 3);
+/// }
+/// ```
+pub struct Database<PA> {
+    inner: crate::Database<PA>,
+}
+
+impl<PA> Database<PA>
+where
+    PA: AsRef<Path> + Send + Sync + Clone + 'static,
+{
+    /// Create a new blocking Database with a mandatory path and an optional file extension.
+    pub fn new(path: PA, extension: Option<&str>) -> Self {
+        Self {
+            inner: crate::Database::new(path, extension),
+        }
+    }
+
+    /// Find documents by filtering with a predicate on a collection.
+    pub fn find<T, P>(&self, collection: &str, predicate: P) -> Result<Vec<T>, DbError>
+    where
+        T: for<'de> Deserialize<'de>,
+        P: FnMut(&T) -> bool,
+    {
+        let documents: Vec<T> = self.inner.config.read_all(&self.inner.cache, collection)?;
+
+        Ok(documents.into_iter().filter(predicate).collect())
+    }
+
+    /// Insert a new document into a collection.
+    pub fn insert<T>(&self, collection: &str, document: T) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+    {
+        let _lock = self.inner.lock_blocking(collection);
+        let mut documents: Vec<T> = self.find(collection, |_| true)?;
+
+        documents.push(document);
+
+        self.write(collection, &documents)
+    }
+
+    /// Update a document by filtering with a predicate on a collection.
+    pub fn update<T, P>(&self, collection: &str, document: T, predicate: P) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+        P: FnMut(&T) -> bool,
+    {
+        let _lock = self.inner.lock_blocking(collection);
+        let documents: Vec<T> = self.find(collection, |_| true)?;
+
+        let documents = replace_first(documents, document, predicate);
+
+        self.write(collection, &documents)
+    }
+
+    /// Delete documents by filtering with a predicate from a collection, returning how many
+    /// documents were deleted.
+    pub fn delete<T, P>(&self, collection: &str, predicate: P) -> Result<usize, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+        P: FnMut(&T) -> bool,
+    {
+        let _lock = self.inner.lock_blocking(collection);
+        let mut documents: Vec<T> = self.find(collection, |_| true)?;
+
+        let deleted = remove_matching(&mut documents, predicate);
+
+        self.write(collection, &documents)?;
+
+        Ok(deleted)
+    }
+
+    /// Rewrite all the documents of a collection.
+    fn write<T>(&self, collection: &str, documents: &[T]) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+    {
+        self.inner.invalidate(collection);
+
+        let skipped = self.inner.config.skipped_rows::<T>(collection)?;
+
+        Ok(self
+            .inner
+            .config
+            .write_all(collection, documents, &skipped)?)
+    }
+}
+
+impl<PA> From<crate::Database<PA>> for Database<PA> {
+    /// Create a blocking Database from a Database, keeping its configuration.
+    fn from(inner: crate::Database<PA>) -> Self {
+        Self { inner }
+    }
+}
@@ -0,0 +1,97 @@
+use aes_gcm::{
+    aead::{Aead, Generate, KeyInit},
+    Aes256Gcm, Key, Nonce,
+};
+use std::io::{self, Read, Write};
+
+/// The length of the nonce that precedes each encrypted frame.
+const NONCE_LEN: usize = 12;
+
+/// An Encryptor collects the bytes written to it and, when finished, writes them to the inner
+/// writer as a single encrypted frame.
+///
+/// A frame is made of a random nonce, the length of the ciphertext as a little endian u64 and the
+/// ciphertext itself. Appending to an encrypted file adds another frame after the existing ones,
+/// so a file can hold any number of frames, which are decrypted in order.
+pub(crate) struct Encryptor<W: Write> {
+    inner: W,
+    cipher: Aes256Gcm,
+    plaintext: Vec<u8>,
+}
+
+impl<W: Write> Encryptor<W> {
+    pub(crate) fn new(inner: W, key: &[u8; 32]) -> Self {
+        Self {
+            inner,
+            cipher: Aes256Gcm::new(&Key::<Aes256Gcm>::from(*key)),
+            plaintext: Vec::new(),
+        }
+    }
+
+    /// Encrypt the collected bytes and write them to the inner writer, which is then returned.
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        let nonce = Nonce::generate();
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, self.plaintext.as_slice())
+            .map_err(|_| io::Error::other("encryption failed"))?;
+
+        self.inner.write_all(&nonce)?;
+        self.inner
+            .write_all(&(ciphertext.len() as u64).to_le_bytes())?;
+        self.inner.write_all(&ciphertext)?;
+
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for Encryptor<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.plaintext.extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Read all the encrypted frames from an input and return their decrypted bytes.
+pub(crate) fn decrypt<R: Read>(mut input: R, key: &[u8; 32]) -> io::Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(&Key::<Aes256Gcm>::from(*key));
+    let mut encrypted = Vec::new();
+    let mut plaintext = Vec::new();
+
+    input.read_to_end(&mut encrypted)?;
+
+    let mut remaining = encrypted.as_slice();
+
+    while !remaining.is_empty() {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid encrypted frame");
+
+        if remaining.len() < NONCE_LEN + 8 {
+            return Err(invalid());
+        }
+
+        let (nonce, rest) = remaining.split_at(NONCE_LEN);
+        let (len, rest) = rest.split_at(8);
+        let len = u64::from_le_bytes(len.try_into().map_err(|_| invalid())?) as usize;
+
+        if rest.len() < len {
+            return Err(invalid());
+        }
+
+        let (ciphertext, rest) = rest.split_at(len);
+        let nonce = Nonce::try_from(nonce).map_err(|_| invalid())?;
+
+        plaintext.extend(
+            cipher
+                .decrypt(&nonce, ciphertext)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "decryption failed"))?,
+        );
+        remaining = rest;
+    }
+
+    Ok(plaintext)
+}
@@ -0,0 +1,152 @@
+use std::{error::Error, fmt, io};
+use tokio::task::JoinError;
+
+/// A DbError describes what went wrong while accessing a Database.
+///
+/// # Examples
+///
+/// ```
+/// use csv_db::{Database, DbError};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// struct User {
+///     id: usize,
+///     first_name: String,
+///     last_name: String,
+///     age: u32,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+/// #   let _ = std::fs::remove_dir_all("data/error");
+/// #   std::fs::create_dir_all("data/error").unwrap();
+/// #   std::fs::write("data/error/users.csv", "id,first_name,last_name,age\none,First,Last,20\n")
+/// #       .unwrap();
+///     let db = Database::new("data/error", None);
+///
+///     match db.find("users", |_: &User| true).await {
+///         Err(DbError::Csv(_)) => println!("Malformed users collection."),
+///         Err(e) => panic!("Unexpected error: {}", e),
+///         Ok(_) => panic!("Expected an error."),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DbError {
+    /// An error reading from or writing to the file system.
+    Io(io::Error),
+    /// An error parsing or serializing CSV records.
+    Csv(csv::Error),
+    /// An error parsing or serializing JSON documents.
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+    /// An error watching a collection for changes.
+    #[cfg(feature = "watch")]
+    Watch(notify::Error),
+    /// An error joining the blocking task that accessed the files.
+    Join(JoinError),
+    /// The requested collection doesn't exist.
+    NotFound { collection: String },
+    /// The collection already exists and would be overwritten.
+    AlreadyExists { collection: String },
+    /// A document with the same unique key already exists in the collection.
+    DuplicateKey { collection: String },
+    /// A document was rejected by a validator.
+    Validation(String),
+    /// A document was updated since it was read, so a versioned update was rejected.
+    Conflict { collection: String },
+    /// The collection has no index, or was written since its index was created.
+    MissingIndex { collection: String },
+    /// The collection file doesn't match its checksum, so it was corrupted on disk.
+    Corruption { collection: String },
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::Io(error) => write!(f, "IO error: {}", error),
+            DbError::Csv(error) => write!(f, "CSV error: {}", error),
+            #[cfg(feature = "json")]
+            DbError::Json(error) => write!(f, "JSON error: {}", error),
+            #[cfg(feature = "watch")]
+            DbError::Watch(error) => write!(f, "watch error: {}", error),
+            DbError::Join(error) => write!(f, "task error: {}", error),
+            DbError::NotFound { collection } => write!(f, "collection not found: {}", collection),
+            DbError::AlreadyExists { collection } => {
+                write!(f, "collection already exists: {}", collection)
+            }
+            DbError::DuplicateKey { collection } => {
+                write!(f, "duplicate key in collection: {}", collection)
+            }
+            DbError::Validation(message) => write!(f, "validation error: {}", message),
+            DbError::Conflict { collection } => {
+                write!(f, "conflicting update in collection: {}", collection)
+            }
+            DbError::MissingIndex { collection } => {
+                write!(
+                    f,
+                    "missing or outdated index for collection: {}",
+                    collection
+                )
+            }
+            DbError::Corruption { collection } => {
+                write!(f, "checksum mismatch in collection: {}", collection)
+            }
+        }
+    }
+}
+
+impl Error for DbError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DbError::Io(error) => Some(error),
+            DbError::Csv(error) => Some(error),
+            #[cfg(feature = "json")]
+            DbError::Json(error) => Some(error),
+            #[cfg(feature = "watch")]
+            DbError::Watch(error) => Some(error),
+            DbError::Join(error) => Some(error),
+            DbError::NotFound { .. }
+            | DbError::AlreadyExists { .. }
+            | DbError::DuplicateKey { .. }
+            | DbError::Validation(_)
+            | DbError::Conflict { .. }
+            | DbError::MissingIndex { .. }
+            | DbError::Corruption { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for DbError {
+    fn from(error: io::Error) -> Self {
+        DbError::Io(error)
+    }
+}
+
+impl From<csv::Error> for DbError {
+    fn from(error: csv::Error) -> Self {
+        DbError::Csv(error)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for DbError {
+    fn from(error: serde_json::Error) -> Self {
+        DbError::Json(error)
+    }
+}
+
+#[cfg(feature = "watch")]
+impl From<notify::Error> for DbError {
+    fn from(error: notify::Error) -> Self {
+        DbError::Watch(error)
+    }
+}
+
+impl From<JoinError> for DbError {
+    fn from(error: JoinError) -> Self {
+        DbError::Join(error)
+    }
+}
@@ -29,6 +29,20 @@
 //! including all parent folders. For instance, if you try to insert into the users collection, the
 //! file `{path}/users.csv`, will be created if it doesn't exist, including all folders in `{path}`.
 //!
+//! Method invocations that rewrite a collection do so atomically. The documents are first
+//! serialized into a temporary file, `{path}/users.csv.tmp` in the example above, which is then
+//! renamed over the collection file, so that a failure midway leaves the collection unchanged.
+//!
+//! # Features
+//!
+//! * `json`: enables `Database::export_json` and `Database::import_json` to export and import
+//!   collections as JSON.
+//! * `mmap`: enables `Database::find_mmap` to read large collections through a memory map.
+//! * `watch`: enables `Database::watch` to watch collections for changes on disk.
+//! * `encryption`: enables `DatabaseBuilder::encryption` to encrypt collections on disk with
+//!   AES-GCM.
+//! * `blocking`: enables the `blocking` module, a synchronous API that needs no async runtime.
+//!
 //! # Examples
 //!
 //! ```
@@ -75,39 +89,7782 @@
 //!     };
 //!
 //!     // Update a user by filtering with a predicate on the users collection.
-//!     db.update("users", user, |u: &&User| u.id == 1)
+//!     db.update("users", user, |u: &User| u.id == 1)
 //!         .await
 //!         .expect("Problem updating user.");
 //!
 //!     // Delete a user by filtering with a predicate from the users collection.
-//!     db.delete("users", |u: &&User| u.id == 1)
+//!     db.delete("users", |u: &User| u.id == 1)
 //!         .await
 //!         .expect("Problem deleting user.");
 //! }
 //! ```
 
-use csv::{Reader, Writer};
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "encryption")]
+mod encryption;
+mod error;
+mod precision;
+mod query;
+mod transaction;
+
+pub use csv::{QuoteStyle, StringRecord, Terminator, Trim};
+pub use error::DbError;
+pub use query::Query;
+pub use transaction::Transaction;
+
+use csv::{ByteRecord, Position, Reader, ReaderBuilder, Writer, WriterBuilder};
+use flate2::{read::MultiGzDecoder, write::GzEncoder};
+use futures::StreamExt;
+use precision::Rounded;
+use rand::RngExt;
 use serde::{Deserialize, Serialize};
-use std::{error::Error, path::Path, sync::Arc};
-use tokio::{task, task::JoinError};
+use std::{
+    borrow::Cow,
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap, HashSet},
+    fs::{File, OpenOptions},
+    hash::Hash,
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    sync::{mpsc, Mutex as AsyncMutex, OwnedMutexGuard},
+    task,
+    task::JoinError,
+};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+
+const STREAM_CAPACITY: usize = 64;
+
+/// The byte order mark that some tools write at the start of UTF-8 files.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// The compression applied to collection files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Plain CSV files (the default).
+    None,
+    /// Gzip compressed CSV files, stored with an additional `gz` extension.
+    Gzip,
+}
+
+/// An Identifiable document has an id that identifies it within a collection.
+///
+/// Implement this trait on a document to use it with [`Database::find_by_id`].
+pub trait Identifiable {
+    /// The type of the id.
+    type Id: PartialEq;
+
+    /// The id of the document.
+    fn id(&self) -> Self::Id;
+}
+
+/// A Timestamped document records when it was created and last updated.
+///
+/// Implement this trait on a document to use it with [`Database::insert_timestamped`] and
+/// [`Database::update_timestamped`], which set the timestamps before writing.
+pub trait Timestamped {
+    /// Set the time when the document was created.
+    fn set_created_at(&mut self, time: SystemTime);
+
+    /// Set the time when the document was last updated.
+    fn set_updated_at(&mut self, time: SystemTime);
+}
+
+/// A SoftDeletable document can be marked as deleted instead of being removed.
+///
+/// Implement this trait on a document to use it with [`Database::soft_delete`],
+/// [`Database::find_active`] and [`Database::purge`].
+pub trait SoftDeletable {
+    /// Whether the document is marked as deleted.
+    fn is_deleted(&self) -> bool;
+
+    /// Mark the document as deleted or not.
+    fn set_deleted(&mut self, deleted: bool);
+}
+
+/// A Versioned document carries a version that is bumped whenever it is updated.
+///
+/// Implement this trait on a document to use it with [`Database::update_versioned`], which
+/// detects conflicting updates by comparing versions.
+pub trait Versioned {
+    /// The version of the document.
+    fn version(&self) -> u64;
+
+    /// Set the version of the document.
+    fn set_version(&mut self, version: u64);
+}
+
+/// An Aggregate summarizes a numeric field over the documents of a collection.
+///
+/// The min, max and avg fields are `None` when no documents were aggregated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aggregate {
+    pub count: usize,
+    pub sum: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub avg: Option<f64>,
+}
+
+/// A RetryPolicy describes how many times, and after which delay, rewriting a collection is
+/// retried when it fails with a transient IO error.
+///
+/// The delay doubles after each attempt. The default policy never retries.
+///
+/// # Examples
+///
+/// ```
+/// use csv_db::RetryPolicy;
+/// use std::{io, time::Duration};
+///
+/// let policy = RetryPolicy {
+///     retries: 3,
+///     delay: Duration::from_millis(1),
+/// };
+///
+/// let mut attempts = 0;
+/// let result = policy.run(|| {
+///     attempts += 1;
+///
+///     if attempts <= 2 {
+///         Err(csv::Error::from(io::Error::from(io::ErrorKind::WouldBlock)))
+///     } else {
+///         Ok(attempts)
+///     }
+/// });
+///
+/// assert_eq!(result.unwrap(), 3);
+///
+/// let mut attempts = 0;
+/// let result: Result<(), _> = policy.run(|| {
+///     attempts += 1;
+///
+///     Err(csv::Error::from(io::Error::from(io::ErrorKind::NotFound)))
+/// });
+///
+/// assert!(result.is_err());
+/// assert_eq!(attempts, 1);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Run an operation, retrying it while it fails with a transient IO error and there are
+    /// retries left.
+    pub fn run<R, F>(&self, mut operation: F) -> Result<R, csv::Error>
+    where
+        F: FnMut() -> Result<R, csv::Error>,
+    {
+        let mut attempt = 0;
+
+        loop {
+            match operation() {
+                Err(error) if attempt < self.retries && is_transient(&error) => {
+                    thread::sleep(self.delay.saturating_mul(2u32.saturating_pow(attempt)));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// A RowError describes a row of a collection that couldn't be read as a document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RowError {
+    /// The 1-based line number of the row in the file.
+    pub line: u64,
+    /// The message of the underlying CSV error.
+    pub message: String,
+}
+
+/// A CollectionMeta summarizes a collection, as returned by [`Database::metadata`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CollectionMeta {
+    /// Whether the file of the collection exists.
+    pub exists: bool,
+    /// The number of rows in the collection, not counting the header.
+    pub row_count: usize,
+    /// The size of the file of the collection in bytes.
+    pub size_bytes: u64,
+    /// The time when the file of the collection was last modified.
+    pub last_modified: Option<SystemTime>,
+    /// The names of the columns in the header of the collection, if it has one.
+    pub header: Option<Vec<String>>,
+}
 
 struct Config<PA> {
     path: PA,
     extension: String,
+    delimiter: u8,
+    cached: bool,
+    compression: Compression,
+    has_headers: bool,
+    quote_style: QuoteStyle,
+    skip_invalid_rows: bool,
+    trim: Trim,
+    flexible: bool,
+    terminator: Option<Terminator>,
+    comment: Option<u8>,
+    buffer_capacity: Option<usize>,
+    retry_policy: RetryPolicy,
+    lenient_schema: bool,
+    float_precision: Option<usize>,
+    verify_checksums: bool,
+    escape: Option<u8>,
+    quote: u8,
+    double_quote: bool,
+    header_renames: HashMap<String, String>,
+    strict_paths: bool,
+    #[cfg(feature = "encryption")]
+    encryption: Option<[u8; 32]>,
 }
 
-/// A Database provides methods to access data.
-///
-/// The config private field is wrapped in an Arc to be shared among threads.
-pub struct Database<PA> {
-    config: Arc<Config<PA>>,
+/// The file a collection is written to, which is encrypted if an encryption key is set.
+enum Sink {
+    File(BufWriter<File>),
+    #[cfg(feature = "encryption")]
+    Encrypted(Box<encryption::Encryptor<BufWriter<File>>>),
 }
 
-impl<PA> Database<PA>
+impl Sink {
+    fn finish(self) -> io::Result<()> {
+        let flush =
+            |buffer: BufWriter<File>| buffer.into_inner().map(|_| ()).map_err(|e| e.into_error());
+
+        match self {
+            Sink::File(buffer) => flush(buffer),
+            #[cfg(feature = "encryption")]
+            Sink::Encrypted(encryptor) => flush((*encryptor).finish()?),
+        }
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::File(file) => file.write(buf),
+            #[cfg(feature = "encryption")]
+            Sink::Encrypted(encryptor) => encryptor.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::File(file) => file.flush(),
+            #[cfg(feature = "encryption")]
+            Sink::Encrypted(encryptor) => encryptor.flush(),
+        }
+    }
+}
+
+/// The output a collection file is written to, which depends on the compression.
+enum Output {
+    Plain(Sink),
+    Gzip(GzEncoder<Sink>),
+}
+
+impl Output {
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Plain(sink) => sink.finish(),
+            Output::Gzip(encoder) => encoder.finish()?.finish(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Plain(file) => file.write(buf),
+            Output::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Plain(file) => file.flush(),
+            Output::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+struct CachedCollection {
+    modified: SystemTime,
+    headers: Option<StringRecord>,
+    records: Vec<StringRecord>,
+}
+
+/// The rows of a collection that are skipped when reading it as documents, each along with how
+/// many valid rows come before it, and the valid rows themselves, which anchor the skipped rows
+/// when the collection is rewritten.
+#[derive(Default)]
+struct SkippedRows {
+    valid: Vec<ByteRecord>,
+    skipped: Vec<(usize, ByteRecord)>,
+}
+
+/// A document ranked by its key, where earlier documents rank higher than later ones with the
+/// same key.
+struct Ranked<K, T> {
+    key: K,
+    position: usize,
+    document: T,
+}
+
+impl<K: Ord, T> Ord for Ranked<K, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then_with(|| other.position.cmp(&self.position))
+    }
+}
+
+impl<K: Ord, T> PartialOrd for Ranked<K, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, T> PartialEq for Ranked<K, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, T> Eq for Ranked<K, T> {}
+
+type Cache = RwLock<HashMap<String, Arc<CachedCollection>>>;
+
+impl<PA> Config<PA>
 where
-    PA: AsRef<Path> + Send + Sync + Clone + 'static,
+    PA: AsRef<Path>,
 {
-    /// Create a new Database with a mandatory path and an optional file extension.
+    fn collection_path(&self, collection: &str) -> PathBuf {
+        self.path
+            .as_ref()
+            .join(format!("{}.{}", collection, self.file_extension()))
+    }
+
+    /// The name of the collection stored in the file at path, or none if the path isn't a file
+    /// with the extension of collections.
+    fn collection_name(&self, path: &Path) -> Option<String> {
+        if !path.is_file() {
+            return None;
+        }
+
+        let suffix = format!(".{}", self.file_extension());
+
+        path.file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_suffix(&suffix))
+            .filter(|n| !n.is_empty())
+            .map(String::from)
+    }
+
+    fn file_extension(&self) -> String {
+        match self.compression {
+            Compression::None => self.extension.clone(),
+            Compression::Gzip => format!("{}.gz", self.extension),
+        }
+    }
+
+    /// Open a reader over a collection, failing only if its file can't be opened.
+    ///
+    /// Callers treat a collection that can't be opened as empty, so errors decoding the file,
+    /// such as a wrong encryption key, are instead returned by the first read of the reader.
+    fn reader(&self, collection: &str) -> Result<Reader<Box<dyn Read + Send>>, csv::Error> {
+        let file = File::open(self.collection_path(collection))?;
+        let input = match self.input(file) {
+            Ok(input) => input,
+            Err(e) => Box::new(Failed(Some(e))),
+        };
+
+        let mut rdr = self.reader_builder().from_reader(input);
+        self.use_field_names(&mut rdr);
+
+        Ok(rdr)
+    }
+
+    /// Wrap the raw bytes of a collection so that they are decompressed and stripped of a byte
+    /// order mark, as needed.
+    fn input<'a, R: Read + Send + 'a>(&self, raw: R) -> io::Result<Box<dyn Read + Send + 'a>> {
+        #[cfg(feature = "encryption")]
+        let raw: Box<dyn Read + Send + 'a> = match &self.encryption {
+            Some(key) => Box::new(io::Cursor::new(encryption::decrypt(raw, key)?)),
+            None => Box::new(raw),
+        };
+
+        let input: Box<dyn Read + Send + 'a> = match self.compression {
+            Compression::None => Box::new(raw),
+            Compression::Gzip => Box::new(MultiGzDecoder::new(raw)),
+        };
+
+        skip_bom(input)
+    }
+
+    /// The headers of a collection, or none if the collection has no headers.
+    fn headers(&self, collection: &str) -> Result<Option<StringRecord>, csv::Error> {
+        if !self.has_headers {
+            return Ok(None);
+        }
+
+        Ok(Some(self.reader(collection)?.headers()?.clone()))
+    }
+
+    fn reader_builder(&self) -> ReaderBuilder {
+        let mut builder = ReaderBuilder::new();
+        builder
+            .delimiter(self.delimiter)
+            .has_headers(self.has_headers)
+            .trim(self.trim)
+            .flexible(self.flexible || self.lenient_schema)
+            .comment(self.comment)
+            .escape(self.escape)
+            .quote(self.quote);
+
+        if let Some(terminator) = self.terminator {
+            builder.terminator(terminator);
+        }
+
+        builder
+    }
+
+    fn writer_builder(&self) -> WriterBuilder {
+        let mut builder = WriterBuilder::new();
+        builder
+            .delimiter(self.delimiter)
+            .has_headers(self.writes_headers())
+            .quote_style(self.quote_style)
+            .quote(self.quote)
+            .double_quote(self.double_quote && self.escape.is_none())
+            // Skipped rows are written back as they were, whatever their length.
+            .flexible(self.skip_invalid_rows);
+
+        if let Some(terminator) = self.terminator {
+            builder.terminator(terminator);
+        }
+
+        if let Some(escape) = self.escape {
+            builder.escape(escape);
+        }
+
+        builder
+    }
+
+    /// Open a writer that appends to the file of a collection, creating it if needed, along with
+    /// whether the file is empty. Headers are only written if the file is empty.
+    fn appender(&self, collection: &str) -> Result<(Writer<Output>, bool), csv::Error> {
+        let path = self.collection_path(collection);
+
+        if let Some(parent_path) = path.parent() {
+            std::fs::create_dir_all(parent_path)?
+        }
+
+        remove_if_exists(&index_path(&path))?;
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let empty = file.metadata()?.len() == 0;
+
+        let wrt = self
+            .writer_builder()
+            .has_headers(empty && self.writes_headers())
+            .from_writer(self.output(file));
+
+        Ok((wrt, empty))
+    }
+
+    fn output(&self, file: File) -> Output {
+        // A zero capacity buffer writes straight through to the file.
+        let buffer = BufWriter::with_capacity(self.buffer_capacity.unwrap_or(0), file);
+
+        #[cfg(feature = "encryption")]
+        let sink = match &self.encryption {
+            Some(key) => Sink::Encrypted(Box::new(encryption::Encryptor::new(buffer, key))),
+            None => Sink::File(buffer),
+        };
+        #[cfg(not(feature = "encryption"))]
+        let sink = Sink::File(buffer);
+
+        match self.compression {
+            Compression::None => Output::Plain(sink),
+            Compression::Gzip => Output::Gzip(GzEncoder::new(sink, flate2::Compression::default())),
+        }
+    }
+
+    /// Serialize a document into a record, with its floats rounded if a float precision is set.
+    fn serialize<W, T>(&self, wrt: &mut Writer<W>, document: &T) -> Result<(), csv::Error>
+    where
+        W: Write,
+        T: Serialize,
+    {
+        match self.float_precision {
+            Some(precision) => wrt.serialize(Rounded::new(document, precision)),
+            None => wrt.serialize(document),
+        }
+    }
+
+    /// Whether writers write the header of a collection by themselves, from the field names of
+    /// its first document, which they don't when header renames are set.
+    fn writes_headers(&self) -> bool {
+        self.has_headers && self.header_renames.is_empty()
+    }
+
+    /// Write the header of a collection before its first document, with the field names of the
+    /// document renamed to their column names, if header renames are set.
+    fn write_renamed_header<W, T>(
+        &self,
+        wrt: &mut Writer<W>,
+        document: &T,
+    ) -> Result<(), csv::Error>
+    where
+        W: Write,
+        T: Serialize,
+    {
+        if !self.has_headers || self.header_renames.is_empty() {
+            return Ok(());
+        }
+
+        // The field names are only known to serde, so they are taken from the header of a copy of
+        // the document serialized in memory.
+        let mut buffer = WriterBuilder::new().from_writer(Vec::new());
+        buffer.serialize(document)?;
+
+        let buffer = buffer.into_inner().map_err(|e| e.into_error())?;
+        let headers = ReaderBuilder::new()
+            .from_reader(buffer.as_slice())
+            .headers()?
+            .clone();
+
+        wrt.write_record(&self.file_headers(&headers))
+    }
+
+    /// Rename the headers of a reader from the column names of the file to the field names of
+    /// the documents, if header renames are set.
+    fn use_field_names<R: Read>(&self, rdr: &mut Reader<R>) {
+        if !self.has_headers || self.header_renames.is_empty() {
+            return;
+        }
+
+        // A reader that fails to read its headers reports the error again on its next read.
+        if let Ok(headers) = rdr.headers() {
+            let headers = headers
+                .iter()
+                .map(|header| {
+                    self.header_renames
+                        .iter()
+                        .find(|(_, renamed)| *renamed == header)
+                        .map_or(header, |(field, _)| field.as_str())
+                })
+                .collect();
+
+            rdr.set_headers(headers);
+        }
+    }
+
+    /// Rename the headers of a collection from the field names of the documents to the column
+    /// names of the file, if header renames are set.
+    fn file_headers(&self, headers: &StringRecord) -> StringRecord {
+        headers
+            .iter()
+            .map(|header| {
+                self.header_renames
+                    .get(header)
+                    .map_or(header, String::as_str)
+            })
+            .collect()
+    }
+
+    /// Deserialize a record into a document. With a lenient schema, the headers of the fields
+    /// missing from a short record are left out, so that they fall back to their serde defaults.
+    fn deserialize<U>(
+        &self,
+        record: &StringRecord,
+        headers: Option<&StringRecord>,
+    ) -> Result<U, csv::Error>
+    where
+        U: for<'de> Deserialize<'de>,
+    {
+        match headers {
+            Some(headers) if self.lenient_schema && record.len() < headers.len() => {
+                let headers: StringRecord = headers.iter().take(record.len()).collect();
+
+                record.deserialize(Some(&headers))
+            }
+            _ => record.deserialize(headers),
+        }
+    }
+
+    /// Whether a row should be kept when reading documents, which is always the case unless it
+    /// is invalid and invalid rows are skipped.
+    fn keep_row<U>(&self, row: &Result<U, csv::Error>) -> bool {
+        match row {
+            Err(error) if self.skip_invalid_rows => !is_row_error(error),
+            _ => true,
+        }
+    }
+
+    /// The cached records of a collection, which are first refreshed from the file whenever its
+    /// modification time differs from the cached one, or none if the collection doesn't exist.
+    fn cached_collection(
+        &self,
+        cache: &Cache,
+        collection: &str,
+    ) -> Result<Option<Arc<CachedCollection>>, csv::Error> {
+        let path = self.collection_path(collection);
+
+        let modified = match std::fs::metadata(&path) {
+            Ok(metadata) => metadata.modified()?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        if let Some(cached) = cache
+            .read()
+            .expect("Collection cache poisoned.")
+            .get(collection)
+            .filter(|cached| cached.modified == modified)
+        {
+            return Ok(Some(cached.clone()));
+        }
+
+        let mut rdr = self.reader(collection)?;
+        let cached = Arc::new(CachedCollection {
+            modified,
+            headers: if self.has_headers {
+                Some(rdr.headers()?.clone())
+            } else {
+                None
+            },
+            records: rdr
+                .records()
+                .filter(|record| self.keep_row(record))
+                .collect::<Result<_, _>>()?,
+        });
+
+        cache
+            .write()
+            .expect("Collection cache poisoned.")
+            .insert(collection.to_string(), cached.clone());
+
+        Ok(Some(cached))
+    }
+
+    /// Atomically replace the file at path with whatever the closure writes, by writing to a
+    /// temporary file first and renaming it over the original one.
+    fn replace<F>(&self, path: &Path, write: F) -> Result<(), csv::Error>
+    where
+        F: FnOnce(&mut Writer<Output>) -> Result<(), csv::Error>,
+    {
+        if let Some(parent_path) = path.parent() {
+            std::fs::create_dir_all(parent_path)?
+        }
+
+        remove_if_exists(&index_path(path))?;
+
+        let tmp_path = tmp_path(path);
+
+        let result = File::create(&tmp_path)
+            .map_err(csv::Error::from)
+            .and_then(|file| {
+                let mut wrt = self.writer_builder().from_writer(self.output(file));
+
+                write(&mut wrt)?;
+                wrt.flush()?;
+                wrt.into_inner().map_err(|e| e.into_error())?.finish()?;
+
+                Ok(())
+            });
+
+        match result {
+            Ok(()) => {
+                std::fs::rename(&tmp_path, path)?;
+
+                Ok(self.store_checksum(path)?)
+            }
+            Err(error) => {
+                let _ = std::fs::remove_file(&tmp_path);
+
+                Err(error)
+            }
+        }
+    }
+
+    /// Read the records of a collection by handing its headers and an iterator over its records
+    /// to a closure, or return a default value when the collection doesn't exist.
+    ///
+    /// This is the path of every read that deserializes documents, so that the records come from
+    /// the cache when enabled, and the collection is verified with [`Config::verify`].
+    fn read_records<R, F>(
+        &self,
+        cache: &Cache,
+        collection: &str,
+        default: R,
+        read: F,
+    ) -> Result<R, DbError>
+    where
+        F: FnOnce(
+            Option<&StringRecord>,
+            &mut dyn Iterator<Item = Result<Cow<'_, StringRecord>, csv::Error>>,
+        ) -> Result<R, csv::Error>,
+    {
+        self.verify(collection)?;
+
+        if self.cached {
+            let cached = match self.cached_collection(cache, collection)? {
+                Some(cached) => cached,
+                None => return Ok(default),
+            };
+
+            return Ok(read(
+                cached.headers.as_ref(),
+                &mut cached
+                    .records
+                    .iter()
+                    .map(|record| Ok(Cow::Borrowed(record))),
+            )?);
+        }
+
+        let mut rdr = match self.reader(collection) {
+            Ok(rdr) => rdr,
+            Err(error) if is_not_found(&error) => return Ok(default),
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(self.read_reader(&mut rdr, read)?)
+    }
+
+    /// Read the records of a reader by handing its headers and an iterator over its records to a
+    /// closure.
+    fn read_reader<I, R, F>(&self, rdr: &mut Reader<I>, read: F) -> Result<R, csv::Error>
+    where
+        I: Read,
+        F: FnOnce(
+            Option<&StringRecord>,
+            &mut dyn Iterator<Item = Result<Cow<'_, StringRecord>, csv::Error>>,
+        ) -> Result<R, csv::Error>,
+    {
+        let headers = if self.has_headers {
+            Some(rdr.headers()?.clone())
+        } else {
+            None
+        };
+
+        read(
+            headers.as_ref(),
+            &mut rdr.records().map(|record| record.map(Cow::Owned)),
+        )
+    }
+
+    /// Read the documents of a collection by handing an iterator over them to a closure, or
+    /// return a default value when the collection doesn't exist.
+    ///
+    /// The documents are deserialized with [`Config::deserialize`], and invalid rows are left out
+    /// when they are skipped.
+    fn read_documents<U, R, F>(
+        &self,
+        cache: &Cache,
+        collection: &str,
+        default: R,
+        read: F,
+    ) -> Result<R, DbError>
+    where
+        U: for<'de> Deserialize<'de>,
+        F: FnOnce(&mut dyn Iterator<Item = Result<U, csv::Error>>) -> Result<R, csv::Error>,
+    {
+        self.read_records(cache, collection, default, |headers, records| {
+            read(
+                &mut records
+                    .map(|record| self.deserialize(&*record?, headers))
+                    .filter(|document| self.keep_row(document)),
+            )
+        })
+    }
+
+    /// Read all the documents of a collection.
+    fn read_all<U>(&self, cache: &Cache, collection: &str) -> Result<Vec<U>, DbError>
+    where
+        U: for<'de> Deserialize<'de>,
+    {
+        self.read_documents(cache, collection, Vec::new(), |documents| {
+            documents.collect()
+        })
+    }
+
+    /// Replace all the documents of a collection, retrying according to the retry policy.
+    ///
+    /// The skipped rows, as found by [`Config::skipped_rows`] before the documents were changed,
+    /// are written back unchanged at their original positions.
+    fn write_all<T: Serialize>(
+        &self,
+        collection: &str,
+        documents: &[T],
+        skipped: &SkippedRows,
+    ) -> Result<(), csv::Error> {
+        let path = self.collection_path(collection);
+
+        self.retry_policy.run(|| {
+            self.replace(&path, |wrt| {
+                if !skipped.skipped.is_empty() {
+                    return self.write_merged(wrt, collection, documents, skipped);
+                }
+
+                if let Some(document) = documents.first() {
+                    self.write_renamed_header(wrt, document)?;
+                }
+
+                for document in documents {
+                    self.serialize(wrt, document)?;
+                }
+
+                Ok(())
+            })
+        })
+    }
+
+    /// Write documents along with the rows of a collection that were skipped when reading them,
+    /// putting each skipped row back after the valid rows that came before it.
+    fn write_merged<T: Serialize>(
+        &self,
+        wrt: &mut Writer<Output>,
+        collection: &str,
+        documents: &[T],
+        skipped: &SkippedRows,
+    ) -> Result<(), csv::Error> {
+        // The documents are serialized in memory first, as the header that a writer adds before
+        // the first serialized document would otherwise come after the skipped rows before it.
+        let mut buffer = WriterBuilder::new()
+            .has_headers(self.has_headers)
+            .from_writer(Vec::new());
+
+        for document in documents {
+            self.serialize(&mut buffer, document)?;
+        }
+
+        let buffer = buffer.into_inner().map_err(|e| e.into_error())?;
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(self.has_headers)
+            .from_reader(buffer.as_slice());
+
+        if self.has_headers {
+            let headers = if documents.is_empty() {
+                self.headers(collection)?
+            } else {
+                Some(rdr.headers()?.clone())
+            };
+
+            if let Some(headers) = headers {
+                wrt.write_record(&self.file_headers(&headers))?;
+            }
+        }
+
+        // Each record is matched to the earliest unchanged valid row after the last matched one,
+        // so that the skipped rows keep their place when documents before them are removed.
+        let mut valid: HashMap<Vec<&[u8]>, Vec<usize>> = HashMap::new();
+
+        for (position, record) in skipped.valid.iter().enumerate() {
+            valid
+                .entry(record.iter().collect())
+                .or_default()
+                .push(position);
+        }
+
+        let mut rows = skipped.skipped.iter().peekable();
+        let mut next = 0;
+
+        for record in rdr.byte_records() {
+            let record = record?;
+            let matched = valid
+                .get(&record.iter().collect::<Vec<_>>())
+                .and_then(|positions| {
+                    positions
+                        .get(positions.partition_point(|position| *position < next))
+                        .copied()
+                });
+
+            while let Some((_, row)) =
+                rows.next_if(|(before, _)| *before <= matched.unwrap_or(next))
+            {
+                wrt.write_byte_record(row)?;
+            }
+
+            wrt.write_byte_record(&record)?;
+
+            if let Some(position) = matched {
+                next = position + 1;
+            }
+        }
+
+        for (_, row) in rows {
+            wrt.write_byte_record(row)?;
+        }
+
+        Ok(())
+    }
+
+    /// The rows of a collection that are skipped when reading it as documents of type U, along
+    /// with its valid rows. No rows are skipped unless invalid rows are.
+    fn skipped_rows<U>(&self, collection: &str) -> Result<SkippedRows, csv::Error>
+    where
+        U: for<'de> Deserialize<'de>,
+    {
+        if !self.skip_invalid_rows {
+            return Ok(SkippedRows::default());
+        }
+
+        let mut rdr = match self.reader(collection) {
+            Ok(rdr) => rdr,
+            Err(error) if is_not_found(&error) => return Ok(SkippedRows::default()),
+            Err(error) => return Err(error),
+        };
+
+        let headers = if self.has_headers {
+            Some(rdr.headers()?.clone())
+        } else {
+            None
+        };
+        let mut record = ByteRecord::new();
+        let mut rows = SkippedRows::default();
+
+        loop {
+            // A row of the wrong length is still read into the record before its error is returned.
+            let valid = match rdr.read_byte_record(&mut record) {
+                Ok(false) => return Ok(rows),
+                Ok(true) => match StringRecord::from_byte_record(record.clone()) {
+                    Ok(fields) => self.deserialize::<U>(&fields, headers.as_ref()).is_ok(),
+                    Err(_) => false,
+                },
+                Err(error) if is_row_error(&error) => false,
+                Err(error) => return Err(error),
+            };
+
+            if valid {
+                rows.valid.push(record.clone());
+            } else {
+                rows.skipped.push((rows.valid.len(), record.clone()));
+            }
+        }
+    }
+
+    /// Verify that the base folder exists, if paths are strict, and that a collection matches its
+    /// checksum, if checksums are verified, before the collection is read.
+    fn verify(&self, collection: &str) -> Result<(), DbError> {
+        if self.strict_paths && !self.path.as_ref().is_dir() {
+            return Err(DbError::NotFound {
+                collection: collection.to_string(),
+            });
+        }
+
+        self.verify_checksum(collection)
+    }
+
+    /// Store the checksum of the file at path after it was written, if checksums are verified.
+    ///
+    /// Otherwise any existing checksum is removed, as it would no longer match the file.
+    fn store_checksum(&self, path: &Path) -> io::Result<()> {
+        if self.verify_checksums {
+            std::fs::write(checksum_path(path), checksum(path)?)
+        } else {
+            remove_if_exists(&checksum_path(path))
+        }
+    }
+
+    /// Verify a collection against its checksum, if checksums are verified.
+    ///
+    /// A collection without a checksum, such as one written before checksums were enabled, or
+    /// without a file, passes the verification.
+    fn verify_checksum(&self, collection: &str) -> Result<(), DbError> {
+        if !self.verify_checksums {
+            return Ok(());
+        }
+
+        let path = self.collection_path(collection);
+        let expected = match std::fs::read_to_string(checksum_path(&path)) {
+            Ok(expected) => expected,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error.into()),
+        };
+
+        match checksum(&path) {
+            Ok(actual) if actual == expected.trim() => Ok(()),
+            Ok(_) => Err(DbError::Corruption {
+                collection: collection.to_string(),
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+/// Skip the UTF-8 byte order mark at the start of an input, if any.
+fn skip_bom<'a>(mut input: Box<dyn Read + Send + 'a>) -> io::Result<Box<dyn Read + Send + 'a>> {
+    let mut prefix = Vec::with_capacity(UTF8_BOM.len());
+    (&mut input)
+        .take(UTF8_BOM.len() as u64)
+        .read_to_end(&mut prefix)?;
+
+    if prefix == UTF8_BOM {
+        Ok(input)
+    } else {
+        Ok(Box::new(io::Cursor::new(prefix).chain(input)))
+    }
+}
+
+/// An input that returns an error on its first read and then reports the end of the input.
+struct Failed(Option<io::Error>);
+
+impl Read for Failed {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        match self.0.take() {
+            Some(e) => Err(e),
+            None => Ok(0),
+        }
+    }
+}
+
+/// Replace the first document matching a predicate with a new document, which is added at the
+/// end if no document matches.
+fn replace_first<T, P>(documents: Vec<T>, document: T, mut predicate: P) -> Vec<T>
+where
+    P: FnMut(&T) -> bool,
+{
+    let mut document = Some(document);
+    let mut documents: Vec<T> = documents
+        .into_iter()
+        .filter_map(|d| {
+            if predicate(&d) {
+                document.take()
+            } else {
+                Some(d)
+            }
+        })
+        .collect();
+
+    if let Some(document) = document {
+        documents.push(document);
+    }
+
+    documents
+}
+
+/// Remove the documents matching a predicate, returning how many were removed.
+fn remove_matching<T, P>(documents: &mut Vec<T>, mut predicate: P) -> usize
+where
+    P: FnMut(&T) -> bool,
+{
+    let len = documents.len();
+
+    documents.retain(|d| !predicate(d));
+
+    len - documents.len()
+}
+
+/// Whether an error is caused by a file that doesn't exist.
+fn is_not_found(error: &csv::Error) -> bool {
+    matches!(error.kind(), csv::ErrorKind::Io(error) if error.kind() == io::ErrorKind::NotFound)
+}
+
+/// Whether an error is caused by a transient IO condition, which may succeed if retried.
+fn is_transient(error: &csv::Error) -> bool {
+    match error.kind() {
+        csv::ErrorKind::Io(error) => matches!(
+            error.kind(),
+            io::ErrorKind::WouldBlock
+                | io::ErrorKind::ResourceBusy
+                | io::ErrorKind::Interrupted
+                | io::ErrorKind::TimedOut
+        ),
+        _ => false,
+    }
+}
+
+/// Whether an error is caused by an invalid row, as opposed to a failure reading the file itself.
+fn is_row_error(error: &csv::Error) -> bool {
+    matches!(
+        error.kind(),
+        csv::ErrorKind::Deserialize { .. }
+            | csv::ErrorKind::Utf8 { .. }
+            | csv::ErrorKind::UnequalLengths { .. }
+    )
+}
+
+/// The path of the checksum file that holds the CRC32 of the bytes of the collection at path.
+pub(crate) fn checksum_path(path: &Path) -> PathBuf {
+    let mut checksum_path = path.as_os_str().to_owned();
+    checksum_path.push(".sum");
+
+    PathBuf::from(checksum_path)
+}
+
+/// The CRC32 of the bytes of a file, formatted as hexadecimal.
+fn checksum(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut crc = flate2::Crc::new();
+    let mut buf = [0; 8192];
+
+    loop {
+        match file.read(&mut buf)? {
+            0 => break,
+            n => crc.update(&buf[..n]),
+        }
+    }
+
+    Ok(format!("{:08x}", crc.sum()))
+}
+
+/// Remove a file, treating a file that doesn't exist as already removed.
+pub(crate) fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// The path of the index file that maps the keys of the collection at path to record positions.
+pub(crate) fn index_path(path: &Path) -> PathBuf {
+    let mut index_path = path.as_os_str().to_owned();
+    index_path.push(".idx");
+
+    PathBuf::from(index_path)
+}
+
+/// The length and modification time of a collection file, which identify the version of the
+/// collection an index was built from.
+fn index_stamp(path: &Path) -> io::Result<StringRecord> {
+    let metadata = std::fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    Ok(StringRecord::from(vec![
+        metadata.len().to_string(),
+        modified.to_string(),
+    ]))
+}
+
+/// The path of the temporary file used to atomically replace the file at path.
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    PathBuf::from(tmp_path)
+}
+
+/// A Database provides methods to access data.
+///
+/// The config private field is wrapped in an Arc to be shared among threads. The locks private
+/// field holds one lock per collection, which serializes the methods that write to it. The cache
+/// private field holds the records of each collection, when caching is enabled.
+pub struct Database<PA> {
+    config: Arc<Config<PA>>,
+    locks: Arc<Mutex<HashMap<String, Arc<AsyncMutex<()>>>>>,
+    cache: Arc<Cache>,
+}
+
+/// A DatabaseBuilder provides chainable methods to configure a Database.
+///
+/// Any option that isn't set keeps the same default used by [`Database::new`].
+pub struct DatabaseBuilder<PA> {
+    config: Config<PA>,
+}
+
+impl<PA> DatabaseBuilder<PA>
+where
+    PA: AsRef<Path> + Send + Sync + Clone + 'static,
+{
+    /// Create a new DatabaseBuilder with a mandatory path for the base folder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::DatabaseBuilder;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/builder");
+    ///     let db = DatabaseBuilder::new("data/builder")
+    ///         .extension("tsv")
+    ///         .delimiter(b'\t')
+    ///         .build();
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user)
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     assert!(std::path::Path::new("data/builder/users.tsv").exists());
+    /// }
+    /// ```
+    pub fn new(path: PA) -> Self {
+        Self {
+            config: Config {
+                path,
+                extension: String::from("csv"),
+                delimiter: b',',
+                cached: false,
+                compression: Compression::None,
+                has_headers: true,
+                quote_style: QuoteStyle::Necessary,
+                skip_invalid_rows: false,
+                trim: Trim::None,
+                flexible: false,
+                terminator: None,
+                comment: None,
+                buffer_capacity: None,
+                retry_policy: RetryPolicy::default(),
+                lenient_schema: false,
+                float_precision: None,
+                verify_checksums: false,
+                escape: None,
+                quote: b'"',
+                double_quote: true,
+                header_renames: HashMap::new(),
+                strict_paths: false,
+                #[cfg(feature = "encryption")]
+                encryption: None,
+            },
+        }
+    }
+
+    /// Set the path for the base folder.
+    pub fn path(mut self, path: PA) -> Self {
+        self.config.path = path;
+        self
+    }
+
+    /// Set the extension of the collection files (csv by default).
+    pub fn extension(mut self, extension: &str) -> Self {
+        self.config.extension = String::from(extension);
+        self
+    }
+
+    /// Set the field delimiter used for reading and writing (comma by default).
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.config.delimiter = delimiter;
+        self
+    }
+
+    /// Set whether the records of each collection are cached in memory (false by default).
+    ///
+    /// When enabled, finding documents on a collection only reads its file if it was modified
+    /// since the last time it was read. The cached records are still deserialized on every find.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::DatabaseBuilder;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/cached");
+    ///     let db = DatabaseBuilder::new("data/cached").cached(true).build();
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user)
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 1);
+    ///
+    ///     // Modify the file externally, which changes its modification time.
+    ///     std::fs::write(
+    ///         "data/cached/users.csv",
+    ///         "id,first_name,last_name,age\n1,First,Last,20\n2,First,Last,30\n",
+    ///     )
+    ///     .unwrap();
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 2);
+    /// }
+    /// ```
+    pub fn cached(mut self, cached: bool) -> Self {
+        self.config.cached = cached;
+        self
+    }
+
+    /// Set the compression applied to collection files (none by default).
+    ///
+    /// With [`Compression::Gzip`] the collection files get an additional `gz` extension, so that
+    /// the users collection is stored in `{path}/users.csv.gz`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Compression, DatabaseBuilder};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/compression");
+    ///     let plain = DatabaseBuilder::new("data/compression").build();
+    ///     let gzip = DatabaseBuilder::new("data/compression")
+    ///         .compression(Compression::Gzip)
+    ///         .build();
+    ///
+    ///     for db in [&plain, &gzip] {
+    ///         let users = (1..=100)
+    ///             .map(|id| User {
+    ///                 id,
+    ///                 first_name: String::from("First"),
+    ///                 last_name: String::from("Last"),
+    ///                 age: 20,
+    ///             })
+    ///             .collect();
+    ///
+    ///         db.insert_many("users", users)
+    ///             .await
+    ///             .expect("Problem inserting users.");
+    ///     }
+    ///
+    ///     let users = gzip
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 100);
+    ///
+    ///     let plain_size = std::fs::metadata("data/compression/users.csv").unwrap().len();
+    ///     let gzip_size = std::fs::metadata("data/compression/users.csv.gz").unwrap().len();
+    ///
+    ///     assert!(gzip_size < plain_size);
+    /// }
+    /// ```
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.config.compression = compression;
+        self
+    }
+
+    /// Set whether collection files have a header row (true by default).
+    ///
+    /// Without a header row, the fields of each document are read and written by position, in
+    /// the same order as they're declared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::DatabaseBuilder;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/has_headers");
+    ///     for has_headers in [true, false] {
+    ///         let db = DatabaseBuilder::new("data/has_headers")
+    ///             .has_headers(has_headers)
+    ///             .build();
+    ///         let collection = format!("users_{}", has_headers);
+    ///
+    ///         let user = User {
+    ///             id: 1,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         };
+    ///
+    ///         db.insert(&collection, user)
+    ///             .await
+    ///             .expect("Problem inserting user.");
+    ///
+    ///         let users = db
+    ///             .find(&collection, |_: &User| true)
+    ///             .await
+    ///             .expect("Problem searching users.");
+    ///
+    ///         assert_eq!(users.len(), 1);
+    ///         assert_eq!(users[0].first_name, "First");
+    ///
+    ///         let path = format!("data/has_headers/{}.csv", collection);
+    ///         let contents = std::fs::read_to_string(path).unwrap();
+    ///
+    ///         assert_eq!(contents.starts_with("id,"), has_headers);
+    ///     }
+    /// }
+    /// ```
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.config.has_headers = has_headers;
+        self
+    }
+
+    /// Set the quoting style used for writing fields ([`QuoteStyle::Necessary`] by default).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{DatabaseBuilder, QuoteStyle};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/quote_style");
+    ///     for (quote_style, collection) in [(QuoteStyle::Always, "always"), (QuoteStyle::Never, "never")] {
+    ///         let db = DatabaseBuilder::new("data/quote_style")
+    ///             .quote_style(quote_style)
+    ///             .build();
+    ///
+    ///         let user = User {
+    ///             id: 1,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last, Jr."),
+    ///             age: 20,
+    ///         };
+    ///
+    ///         db.insert(collection, user)
+    ///             .await
+    ///             .expect("Problem inserting user.");
+    ///     }
+    ///
+    ///     assert_eq!(
+    ///         std::fs::read_to_string("data/quote_style/always.csv").unwrap(),
+    ///         "\"id\",\"first_name\",\"last_name\",\"age\"\n\"1\",\"First\",\"Last, Jr.\",\"20\"\n"
+    ///     );
+    ///     assert_eq!(
+    ///         std::fs::read_to_string("data/quote_style/never.csv").unwrap(),
+    ///         "id,first_name,last_name,age\n1,First,Last, Jr.,20\n"
+    ///     );
+    /// }
+    /// ```
+    pub fn quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.config.quote_style = quote_style;
+        self
+    }
+
+    /// Set whether finding documents skips invalid rows instead of failing (false by default).
+    ///
+    /// Invalid rows are those that can't be deserialized into a document, aren't valid UTF-8 or
+    /// have a different number of fields than the others. Errors reading the file itself are
+    /// never skipped.
+    ///
+    /// Skipped rows are kept when a write such as an insert, an update or a delete rewrites the
+    /// collection, unchanged and at their original positions, so that skipping them never loses
+    /// data. Writes that replace the whole contents of the collection remove them, namely
+    /// [`Database::replace_all`], `Database::import_json`, [`Database::write_raw`],
+    /// [`Database::write_with_headers`], [`Database::truncate`], [`Database::restore`] and
+    /// [`Database::import_all`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::DatabaseBuilder;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/skip_invalid_rows");
+    /// #   std::fs::create_dir_all("data/skip_invalid_rows").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/skip_invalid_rows/users.csv",
+    /// #       "id,first_name,last_name,age\n1,First,Last,20\ntwo,First,Last,20\n3,First,Last,20\n",
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = DatabaseBuilder::new("data/skip_invalid_rows")
+    ///         .skip_invalid_rows(true)
+    ///         .build();
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![1, 3]);
+    ///
+    ///     let adults = db
+    ///         .count_where("users", |u: &User| u.age >= 18)
+    ///         .await
+    ///         .expect("Problem counting users.");
+    ///
+    ///     assert_eq!(adults, 2);
+    ///
+    ///     let user = User {
+    ///         id: 4,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user)
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     assert_eq!(
+    ///         std::fs::read_to_string("data/skip_invalid_rows/users.csv").unwrap(),
+    ///         "id,first_name,last_name,age\n1,First,Last,20\ntwo,First,Last,20\n3,First,Last,20\n4,First,Last,20\n"
+    ///     );
+    ///
+    ///     db.delete("users", |u: &User| u.id == 1)
+    ///         .await
+    ///         .expect("Problem deleting user.");
+    ///
+    ///     assert_eq!(
+    ///         std::fs::read_to_string("data/skip_invalid_rows/users.csv").unwrap(),
+    ///         "id,first_name,last_name,age\ntwo,First,Last,20\n3,First,Last,20\n4,First,Last,20\n"
+    ///     );
+    /// }
+    /// ```
+    pub fn skip_invalid_rows(mut self, skip_invalid_rows: bool) -> Self {
+        self.config.skip_invalid_rows = skip_invalid_rows;
+        self
+    }
+
+    /// Set which whitespace is trimmed from headers and fields when reading ([`Trim::None`] by
+    /// default).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{DatabaseBuilder, Trim};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/trim");
+    /// #   std::fs::create_dir_all("data/trim").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/trim/users.csv",
+    /// #       "id, first_name, last_name, age\n 1 , First , Last , 20 \n",
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = DatabaseBuilder::new("data/trim").trim(Trim::All).build();
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(
+    ///         users,
+    ///         vec![User {
+    ///             id: 1,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         }]
+    ///     );
+    /// }
+    /// ```
+    pub fn trim(mut self, trim: Trim) -> Self {
+        self.config.trim = trim;
+        self
+    }
+
+    /// Set whether records with a different number of fields than the others are accepted when
+    /// reading (false by default).
+    ///
+    /// Combined with `#[serde(default, deserialize_with = "csv::invalid_option")]` on optional
+    /// fields this allows reading rows with missing trailing fields.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::DatabaseBuilder;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     #[serde(default, deserialize_with = "csv::invalid_option")]
+    ///     age: Option<u32>,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/flexible");
+    /// #   std::fs::create_dir_all("data/flexible").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/flexible/users.csv",
+    /// #       "id,first_name,last_name,age\n1,First,Last,20\n2,First,Last\n",
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = DatabaseBuilder::new("data/flexible").flexible(true).build();
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.iter().map(|u| u.age).collect::<Vec<_>>(), vec![Some(20), None]);
+    /// }
+    /// ```
+    pub fn flexible(mut self, flexible: bool) -> Self {
+        self.config.flexible = flexible;
+        self
+    }
+
+    /// Set the record terminator used for reading and writing.
+    ///
+    /// By default records are written with `\n` and read with any of `\r`, `\n` or `\r\n`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{DatabaseBuilder, Terminator};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/terminator");
+    ///     let db = DatabaseBuilder::new("data/terminator")
+    ///         .terminator(Terminator::CRLF)
+    ///         .build();
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user).await.expect("Problem inserting user.");
+    ///
+    ///     assert_eq!(
+    ///         std::fs::read("data/terminator/users.csv").unwrap(),
+    ///         b"id,first_name,last_name,age\r\n1,First,Last,20\r\n"
+    ///     );
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 1);
+    /// }
+    /// ```
+    pub fn terminator(mut self, terminator: Terminator) -> Self {
+        self.config.terminator = Some(terminator);
+        self
+    }
+
+    /// Set the byte that marks lines to be ignored as comments when reading (none by default).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::DatabaseBuilder;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/comment");
+    /// #   std::fs::create_dir_all("data/comment").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/comment/users.csv",
+    /// #       "id,first_name,last_name,age\n# First comment\n1,First,Last,20\n# Second comment\n2,First,Last,21\n",
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = DatabaseBuilder::new("data/comment")
+    ///         .comment(Some(b'#'))
+    ///         .build();
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![1, 2]);
+    /// }
+    /// ```
+    pub fn comment(mut self, comment: Option<u8>) -> Self {
+        self.config.comment = comment;
+        self
+    }
+
+    /// Set the capacity of the buffer between the CSV writer and the file (none by default).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::DatabaseBuilder;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/buffer_capacity");
+    ///     let db = DatabaseBuilder::new("data/buffer_capacity")
+    ///         .buffer_capacity(Some(1024 * 1024))
+    ///         .build();
+    ///
+    ///     let users: Vec<User> = (0..10_000)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 10_000);
+    ///     assert!(users.iter().enumerate().all(|(i, u)| u.id == i));
+    /// }
+    /// ```
+    pub fn buffer_capacity(mut self, buffer_capacity: Option<usize>) -> Self {
+        self.config.buffer_capacity = buffer_capacity;
+        self
+    }
+
+    /// Set the policy for retrying rewrites of a collection that fail with transient IO errors
+    /// (never retried by default).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{DatabaseBuilder, RetryPolicy};
+    /// use serde::{Deserialize, Serialize};
+    /// use std::time::Duration;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/retry_policy");
+    ///     let db = DatabaseBuilder::new("data/retry_policy")
+    ///         .retry_policy(RetryPolicy {
+    ///             retries: 3,
+    ///             delay: Duration::from_millis(10),
+    ///         })
+    ///         .build();
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user).await.expect("Problem inserting user.");
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 1);
+    /// }
+    /// ```
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.config.retry_policy = retry_policy;
+        self
+    }
+
+    /// Set whether finding documents accepts rows with missing trailing fields, which then take
+    /// their serde defaults (false by default).
+    ///
+    /// This allows adding a field marked with `#[serde(default)]` to a document, while rows
+    /// written before the field existed keep working.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::DatabaseBuilder;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    ///     #[serde(default)]
+    ///     email: String,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/lenient_schema");
+    /// #   std::fs::create_dir_all("data/lenient_schema").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/lenient_schema/users.csv",
+    /// #       "id,first_name,last_name,age,email\n1,First,Last,20\n2,First,Last,21,user@example.com\n",
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = DatabaseBuilder::new("data/lenient_schema")
+    ///         .lenient_schema(true)
+    ///         .build();
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(
+    ///         users.iter().map(|u| u.email.as_str()).collect::<Vec<_>>(),
+    ///         vec!["", "user@example.com"]
+    ///     );
+    /// }
+    /// ```
+    pub fn lenient_schema(mut self, lenient_schema: bool) -> Self {
+        self.config.lenient_schema = lenient_schema;
+        self
+    }
+
+    /// Set the number of decimal places that floats are written with (none by default).
+    ///
+    /// Without a precision, floats are written with as many digits as needed to read them back
+    /// exactly. With one, every float of a document, including those of nested fields, is
+    /// rounded to that many decimal places when it's written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::DatabaseBuilder;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct Score {
+    ///     id: usize,
+    ///     value: f64,
+    ///     bonus: Option<f32>,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/float_precision");
+    ///     let db = DatabaseBuilder::new("data/float_precision")
+    ///         .float_precision(Some(2))
+    ///         .build();
+    ///
+    ///     let scores = vec![
+    ///         Score {
+    ///             id: 1,
+    ///             value: 3.14159,
+    ///             bonus: Some(0.5),
+    ///         },
+    ///         Score {
+    ///             id: 2,
+    ///             value: 0.1 + 0.2,
+    ///             bonus: None,
+    ///         },
+    ///     ];
+    ///
+    ///     db.insert_many("scores", scores)
+    ///         .await
+    ///         .expect("Problem inserting scores.");
+    ///
+    ///     assert_eq!(
+    ///         std::fs::read_to_string("data/float_precision/scores.csv").unwrap(),
+    ///         "id,value,bonus\n1,3.14,0.50\n2,0.30,\n"
+    ///     );
+    /// }
+    /// ```
+    pub fn float_precision(mut self, float_precision: Option<usize>) -> Self {
+        self.config.float_precision = float_precision;
+        self
+    }
+
+    /// Encrypt collection files on disk with AES-256-GCM, using the given 256-bit key.
+    ///
+    /// Each write seals its records under a fresh random nonce, so appends add a new encrypted
+    /// frame to the end of the file rather than rewriting it. Compression, if any, is applied
+    /// before encryption. Reading an encrypted collection with a different key, or without one,
+    /// fails. Indexes aren't supported on encrypted collections.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::DatabaseBuilder;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/encryption");
+    ///     let db = DatabaseBuilder::new("data/encryption")
+    ///         .encryption([7; 32])
+    ///         .build();
+    ///
+    ///     for id in 1..=2 {
+    ///         let user = User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         };
+    ///
+    ///         db.insert("users", user).await.expect("Problem inserting user.");
+    ///     }
+    ///
+    ///     let raw = std::fs::read("data/encryption/users.csv").unwrap();
+    ///
+    ///     assert!(!raw.windows(5).any(|window| window == b"First"));
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 2);
+    ///     assert_eq!(users[1].id, 2);
+    ///
+    ///     let other = DatabaseBuilder::new("data/encryption")
+    ///         .encryption([8; 32])
+    ///         .build();
+    ///
+    ///     assert!(other.find("users", |_: &User| true).await.is_err());
+    /// }
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn encryption(mut self, key: [u8; 32]) -> Self {
+        self.config.encryption = Some(key);
+        self
+    }
+
+    /// Verify collections against a checksum of their files when they are searched, to detect
+    /// corruption on disk.
+    ///
+    /// Every write stores the CRC32 of the collection file in a sidecar file with an additional
+    /// `sum` extension, and `find` recomputes it, failing with [`DbError::Corruption`] if they
+    /// differ. Appends verify the collection in the same way before adding to it, so that a
+    /// corrupted file never gets a matching checksum. A collection without a sidecar, such as one
+    /// written before checksums were enabled, isn't verified until it is next written. Writes
+    /// made without this option remove the sidecar, since it would no longer match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{DatabaseBuilder, DbError};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/verify_checksums");
+    ///     let db = DatabaseBuilder::new("data/verify_checksums")
+    ///         .verify_checksums(true)
+    ///         .build();
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user).await.expect("Problem inserting user.");
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 1);
+    ///
+    ///     let path = "data/verify_checksums/users.csv";
+    ///     let mut bytes = std::fs::read(path).unwrap();
+    ///     let last = bytes.len() - 2;
+    ///     bytes[last] = b'9';
+    ///     std::fs::write(path, bytes).unwrap();
+    ///
+    ///     assert!(matches!(
+    ///         db.find("users", |_: &User| true).await,
+    ///         Err(DbError::Corruption { .. })
+    ///     ));
+    ///
+    ///     let user = User {
+    ///         id: 2,
+    ///         first_name: String::from("Second"),
+    ///         last_name: String::from("Last"),
+    ///         age: 30,
+    ///     };
+    ///
+    ///     assert!(matches!(
+    ///         db.append("users", user).await,
+    ///         Err(DbError::Corruption { .. })
+    ///     ));
+    /// }
+    /// ```
+    pub fn verify_checksums(mut self, verify_checksums: bool) -> Self {
+        self.config.verify_checksums = verify_checksums;
+        self
+    }
+
+    /// Set the byte that escapes quotes inside quoted fields, instead of doubling them (none by
+    /// default).
+    ///
+    /// When set, quotes are written escaped with this byte rather than doubled, and are read
+    /// either way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::DatabaseBuilder;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/escape");
+    /// #   std::fs::create_dir_all("data/escape").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/escape/users.csv",
+    /// #       "id,first_name,last_name,age\n1,\"First \\\"Nick\\\"\",Last,20\n",
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = DatabaseBuilder::new("data/escape")
+    ///         .escape(Some(b'\\'))
+    ///         .build();
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users[0].first_name, "First \"Nick\"");
+    ///
+    ///     db.insert_many("copies", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     assert_eq!(
+    ///         std::fs::read_to_string("data/escape/copies.csv").unwrap(),
+    ///         "id,first_name,last_name,age\n1,\"First \\\"Nick\\\"\",Last,20\n"
+    ///     );
+    /// }
+    /// ```
+    pub fn escape(mut self, escape: Option<u8>) -> Self {
+        self.config.escape = escape;
+        self
+    }
+
+    /// Set the byte that quotes fields when reading and writing (`"` by default).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::DatabaseBuilder;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/quote");
+    ///     let db = DatabaseBuilder::new("data/quote").quote(b'\'').build();
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last, Jr"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user).await.expect("Problem inserting user.");
+    ///
+    ///     assert_eq!(
+    ///         std::fs::read_to_string("data/quote/users.csv").unwrap(),
+    ///         "id,first_name,last_name,age\n1,First,'Last, Jr',20\n"
+    ///     );
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users[0].last_name, "Last, Jr");
+    /// }
+    /// ```
+    pub fn quote(mut self, quote: u8) -> Self {
+        self.config.quote = quote;
+        self
+    }
+
+    /// Set whether quotes inside quoted fields are escaped by doubling them when writing (true by
+    /// default).
+    ///
+    /// If disabled without an [`escape`](DatabaseBuilder::escape) byte, quotes are written as they
+    /// are. Together with [`QuoteStyle::Never`] this writes fields completely raw, in which case
+    /// fields that contain the delimiter, a quote or a line terminator can no longer be told
+    /// apart from the surrounding records, and keeping them out of the documents is up to the
+    /// caller.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{DatabaseBuilder, QuoteStyle};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/double_quote");
+    ///     let db = DatabaseBuilder::new("data/double_quote")
+    ///         .quote_style(QuoteStyle::Never)
+    ///         .double_quote(false)
+    ///         .build();
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last, Jr"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user).await.expect("Problem inserting user.");
+    ///
+    ///     let raw = std::fs::read_to_string("data/double_quote/users.csv").unwrap();
+    ///
+    ///     assert_eq!(raw, "id,first_name,last_name,age\n1,First,Last, Jr,20\n");
+    ///     assert!(!raw.contains('"'));
+    /// }
+    /// ```
+    pub fn double_quote(mut self, double_quote: bool) -> Self {
+        self.config.double_quote = double_quote;
+        self
+    }
+
+    /// Rename the columns of collection files from the field names of the documents, with a map
+    /// from field name to column name.
+    ///
+    /// Fields are written under their renamed columns, and columns are read back into their
+    /// fields, so documents round trip as if the fields had the column names. Fields that aren't
+    /// in the map keep their names. Methods that return column names, like
+    /// [`Database::headers`], return the field names.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::DatabaseBuilder;
+    /// use serde::{Deserialize, Serialize};
+    /// use std::collections::HashMap;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/header_renames");
+    ///     let renames = HashMap::from([
+    ///         (String::from("first_name"), String::from("First Name")),
+    ///         (String::from("last_name"), String::from("Last Name")),
+    ///     ]);
+    ///     let db = DatabaseBuilder::new("data/header_renames")
+    ///         .header_renames(renames)
+    ///         .build();
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user).await.expect("Problem inserting user.");
+    ///
+    ///     assert_eq!(
+    ///         std::fs::read_to_string("data/header_renames/users.csv").unwrap(),
+    ///         "id,First Name,Last Name,age\n1,First,Last,20\n"
+    ///     );
+    ///
+    ///     let users = db
+    ///         .find("users", |u: &User| u.first_name == "First")
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 1);
+    ///     assert_eq!(users[0].last_name, "Last");
+    /// }
+    /// ```
+    pub fn header_renames(mut self, header_renames: HashMap<String, String>) -> Self {
+        self.config.header_renames = header_renames;
+        self
+    }
+
+    /// Fail searches with [`DbError::NotFound`] when the base folder of the database doesn't
+    /// exist, instead of finding no documents (false by default).
+    ///
+    /// This tells a misconfigured path apart from an empty or missing collection, which is still
+    /// found to have no documents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{DatabaseBuilder, DbError};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/strict_paths");
+    ///     let lenient = DatabaseBuilder::new("data/strict_paths").build();
+    ///     let strict = DatabaseBuilder::new("data/strict_paths")
+    ///         .strict_paths(true)
+    ///         .build();
+    ///
+    ///     let users = lenient
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert!(users.is_empty());
+    ///     assert!(matches!(
+    ///         strict.find("users", |_: &User| true).await,
+    ///         Err(DbError::NotFound { .. })
+    ///     ));
+    ///
+    ///     std::fs::create_dir_all("data/strict_paths").unwrap();
+    ///
+    ///     let users = strict
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert!(users.is_empty());
+    /// }
+    /// ```
+    pub fn strict_paths(mut self, strict_paths: bool) -> Self {
+        self.config.strict_paths = strict_paths;
+        self
+    }
+
+    /// Build a Database using the current configuration.
+    pub fn build(self) -> Database<PA> {
+        Database {
+            config: Arc::new(self.config),
+            locks: Arc::new(Mutex::new(HashMap::new())),
+            cache: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+}
+
+impl<PA> Database<PA>
+where
+    PA: AsRef<Path> + Send + Sync + Clone + 'static,
+{
+    /// Create a new Database with a mandatory path and an optional file extension.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let db = Database::new("data", None);
+    /// }
+    /// ```
+    pub fn new(path: PA, extension: Option<&str>) -> Self {
+        DatabaseBuilder::new(path)
+            .extension(extension.unwrap_or("csv"))
+            .build()
+    }
+
+    /// Open an existing Database with a mandatory path and an optional file extension, failing
+    /// if the path doesn't exist or isn't a directory.
+    ///
+    /// Unlike [`Database::new`], which doesn't access the file system until the Database is used,
+    /// this makes a misconfigured path fail fast.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Database, DbError};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/open");
+    /// #   std::fs::create_dir_all("data/open").unwrap();
+    ///     let db = Database::open("data/open", None);
+    ///
+    ///     assert!(db.is_ok());
+    ///
+    ///     match Database::open("data/open/missing", None) {
+    ///         Err(DbError::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::NotFound),
+    ///         _ => panic!("Expected the path to be missing."),
+    ///     }
+    /// }
+    /// ```
+    pub fn open(path: PA, extension: Option<&str>) -> Result<Self, DbError> {
+        if !std::fs::metadata(path.as_ref())?.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                format!("not a directory: {}", path.as_ref().display()),
+            )
+            .into());
+        }
+
+        Ok(Self::new(path, extension))
+    }
+
+    /// Create a new Database with a mandatory path, an optional file extension and a field
+    /// delimiter, which is used both for reading and writing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/delimiter");
+    ///     let db = Database::with_delimiter("data/delimiter", None, b';');
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user)
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     let users = db
+    ///         .find("users", |u: &User| u.id == 1)
+    ///         .await
+    ///         .expect("Problem searching user.");
+    ///
+    ///     assert_eq!(users.len(), 1);
+    ///     assert_eq!(users[0].first_name, "First");
+    /// }
+    /// ```
+    pub fn with_delimiter(path: PA, extension: Option<&str>, delimiter: u8) -> Self {
+        DatabaseBuilder::new(path)
+            .extension(extension.unwrap_or("csv"))
+            .delimiter(delimiter)
+            .build()
+    }
+
+    /// Find documents by filtering with a predicate on a collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let db = Database::new("data", None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     let adults = db
+    ///         .find("users", |u: &User| u.age >= 18)
+    ///         .await
+    ///         .expect("Problem searching user.");
+    /// }
+    /// ```
+    ///
+    /// Documents are always found in the order they are stored in the collection. Inserting a
+    /// document adds it at the end, while updating a document keeps it in its position:
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find");
+    ///     let db = Database::new("data/find", None);
+    ///
+    ///     for id in [3, 1, 4, 2] {
+    ///         let user = User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         };
+    ///
+    ///         db.insert("users", user).await.expect("Problem inserting user.");
+    ///     }
+    ///
+    ///     for id in [4, 3] {
+    ///         let user = User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 21,
+    ///         };
+    ///
+    ///         db.update("users", user, |u: &User| u.id == id)
+    ///             .await
+    ///             .expect("Problem updating user.");
+    ///     }
+    ///
+    ///     let users = db
+    ///         .find("users", |u: &User| u.age >= 18)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![3, 1, 4, 2]);
+    /// }
+    /// ```
+    ///
+    /// A UTF-8 byte order mark at the start of a collection, as written by some spreadsheet
+    /// applications, is skipped instead of being read as part of the first column name:
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_bom");
+    /// #   std::fs::create_dir_all("data/find_bom").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/find_bom/users.csv",
+    /// #       b"\xEF\xBB\xBFid,first_name,last_name,age\n1,First,Last,20\n",
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = Database::new("data/find_bom", None);
+    ///
+    ///     let users = db
+    ///         .find("users", |u: &User| u.id == 1)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 1);
+    /// }
+    /// ```
+    pub async fn find<T, P>(&self, collection: &str, predicate: P) -> Result<Vec<T>, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool,
+    {
+        self.find_projected(collection, predicate).await
+    }
+
+    /// Find documents by filtering with a predicate on a collection, deserializing only a subset
+    /// of their fields.
+    ///
+    /// The fields of `U` are matched by name against the header of the collection, so each of
+    /// them must have the same name as one of its columns. Any other columns are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// struct NameOnly {
+    ///     first_name: String,
+    ///     last_name: String,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_projected");
+    ///     let db = Database::new("data/find_projected", None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user)
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     let names = db
+    ///         .find_projected("users", |_: &NameOnly| true)
+    ///         .await
+    ///         .expect("Problem searching user.");
+    ///
+    ///     assert_eq!(
+    ///         names,
+    ///         vec![NameOnly {
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///         }]
+    ///     );
+    /// }
+    /// ```
+    pub async fn find_projected<U, P>(
+        &self,
+        collection: &str,
+        predicate: P,
+    ) -> Result<Vec<U>, DbError>
+    where
+        U: for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&U) -> bool,
+    {
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let cache = self.cache.clone();
+        let results: Result<Result<Vec<U>, DbError>, _> =
+            task::spawn_blocking(move || config.read_all(&cache, &collection)).await;
+
+        Ok(results??.into_iter().filter(predicate).collect())
+    }
+
+    /// Find documents by filtering with a predicate on several collections at once, keyed by
+    /// collection name.
+    ///
+    /// The collections are read concurrently, each with its own copy of the predicate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_many");
+    ///     let db = Database::new("data/find_many", None);
+    ///
+    ///     for (collection, age) in [("users", 20), ("users", 10), ("admins", 30)] {
+    ///         let user = User {
+    ///             id: 1,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age,
+    ///         };
+    ///
+    ///         db.insert(collection, user)
+    ///             .await
+    ///             .expect("Problem inserting user.");
+    ///     }
+    ///
+    ///     let adults = db
+    ///         .find_many(&["users", "admins"], |u: &User| u.age >= 18)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(adults["users"].len(), 1);
+    ///     assert_eq!(adults["admins"].len(), 1);
+    /// }
+    /// ```
+    pub async fn find_many<T, P>(
+        &self,
+        collections: &[&str],
+        predicate: P,
+    ) -> Result<HashMap<String, Vec<T>>, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool + Clone,
+    {
+        let results = futures::future::try_join_all(
+            collections
+                .iter()
+                .map(|collection| self.find(collection, predicate.clone())),
+        )
+        .await?;
+
+        Ok(collections
+            .iter()
+            .map(|collection| collection.to_string())
+            .zip(results)
+            .collect())
+    }
+
+    /// Find all documents matching a predicate on a collection, along with the rows that couldn't
+    /// be read as documents.
+    ///
+    /// Invalid rows are reported instead of failing the whole read, so that data quality issues
+    /// can be surfaced while still returning the valid documents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_with_errors");
+    /// #   std::fs::create_dir_all("data/find_with_errors").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/find_with_errors/users.csv",
+    /// #       "id,first_name,last_name,age\n1,First,Last,20\ntwo,First,Last,20\n3,First,Last,20\n4,First,Last,old\n",
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = Database::new("data/find_with_errors", None);
+    ///
+    ///     let (users, errors) = db
+    ///         .find_with_errors("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 2);
+    ///     assert_eq!(errors.iter().map(|e| e.line).collect::<Vec<_>>(), vec![3, 5]);
+    /// }
+    /// ```
+    pub async fn find_with_errors<T, P>(
+        &self,
+        collection: &str,
+        mut predicate: P,
+    ) -> Result<(Vec<T>, Vec<RowError>), DbError>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool + Send + 'static,
+    {
+        let config = self.config.clone();
+
+        // Invalid rows are read directly from the file, to be reported even when they are skipped.
+        self.read(collection, (Vec::new(), Vec::new()), move |rdr| {
+            let mut documents = Vec::new();
+            let mut errors = Vec::new();
+            let mut record = StringRecord::new();
+            let headers = if rdr.has_headers() {
+                Some(rdr.headers()?.clone())
+            } else {
+                None
+            };
+
+            loop {
+                let line = rdr.position().line();
+
+                let result = match rdr.read_record(&mut record) {
+                    Ok(true) => config.deserialize(&record, headers.as_ref()),
+                    Ok(false) => break,
+                    Err(error) => Err(error),
+                };
+
+                match result {
+                    Ok(document) => {
+                        if predicate(&document) {
+                            documents.push(document);
+                        }
+                    }
+                    Err(error) if is_row_error(&error) => errors.push(RowError {
+                        line: error.position().map_or(line, |pos| pos.line()),
+                        message: error.to_string(),
+                    }),
+                    Err(error) => return Err(error),
+                }
+            }
+
+            Ok((documents, errors))
+        })
+        .await
+    }
+
+    /// Find documents by filtering with a predicate on a collection, stopping at the first row
+    /// that can't be read but keeping the documents found before it.
+    ///
+    /// The error of that row, if any, is returned along with the documents, so that the caller can
+    /// decide whether to use them. Unlike [`Database::find_with_errors`], no rows are read after
+    /// the error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_partial");
+    /// #   std::fs::create_dir_all("data/find_partial").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/find_partial/users.csv",
+    /// #       "id,first_name,last_name,age\n1,First,Last,20\n2,First,Last,21\n3,First,Last,old\n4,First,Last,23\n",
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = Database::new("data/find_partial", None);
+    ///
+    ///     let (users, error) = db
+    ///         .find_partial("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 2);
+    ///     assert_eq!(users[1].id, 2);
+    ///     assert!(error.is_some());
+    /// }
+    /// ```
+    pub async fn find_partial<T, P>(
+        &self,
+        collection: &str,
+        mut predicate: P,
+    ) -> Result<(Vec<T>, Option<DbError>), DbError>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool + Send + 'static,
+    {
+        self.read_documents(collection, (Vec::new(), None), move |documents| {
+            let mut found = Vec::new();
+
+            for document in documents {
+                match document {
+                    Ok(document) => {
+                        if predicate(&document) {
+                            found.push(document);
+                        }
+                    }
+                    Err(error) => return Ok((found, Some(error.into()))),
+                }
+            }
+
+            Ok((found, None))
+        })
+        .await
+    }
+
+    /// Find all documents matching a predicate on a collection, along with their 1-based record
+    /// positions in the collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_indexed");
+    ///     let db = Database::new("data/find_indexed", None);
+    ///
+    ///     for id in 1..=4 {
+    ///         let user = User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         };
+    ///
+    ///         db.insert("users", user).await.expect("Problem inserting user.");
+    ///     }
+    ///
+    ///     let users = db
+    ///         .find_indexed("users", |u: &User| u.id % 2 == 0)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(
+    ///         users.iter().map(|(i, u)| (*i, u.id)).collect::<Vec<_>>(),
+    ///         vec![(2, 2), (4, 4)]
+    ///     );
+    /// }
+    /// ```
+    pub async fn find_indexed<T, P>(
+        &self,
+        collection: &str,
+        mut predicate: P,
+    ) -> Result<Vec<(u64, T)>, DbError>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool + Send + 'static,
+    {
+        self.read_documents(collection, Vec::new(), move |documents| {
+            let mut found = Vec::new();
+
+            for (index, document) in documents.enumerate() {
+                let document = document?;
+
+                if predicate(&document) {
+                    found.push((index as u64 + 1, document));
+                }
+            }
+
+            Ok(found)
+        })
+        .await
+    }
+
+    /// Find documents by filtering with a predicate on a collection, reading it through a memory
+    /// map.
+    ///
+    /// The operating system pages the file in lazily instead of it being buffered through regular
+    /// reads, which suits large collections that don't change while they are read. The lock of
+    /// the collection is held during the read, so other writes through this Database wait for it
+    /// to finish, but the file must not be modified by other processes or Database instances in
+    /// the meantime, as the mapped bytes would change under the reader.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_mmap");
+    ///     let db = Database::new("data/find_mmap", None);
+    ///
+    ///     let users = (0..50_000)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: (id % 100) as u32,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let mapped: Vec<User> = db
+    ///         .find_mmap("users", |u: &User| u.age >= 18)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///     let read: Vec<User> = db
+    ///         .find("users", |u: &User| u.age >= 18)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(mapped.len(), 41_000);
+    ///     assert_eq!(mapped, read);
+    /// }
+    /// ```
+    #[cfg(feature = "mmap")]
+    pub async fn find_mmap<T, P>(&self, collection: &str, predicate: P) -> Result<Vec<T>, DbError>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool,
+    {
+        let _lock = self.lock(collection).await;
+
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let results: Result<Result<Vec<T>, DbError>, JoinError> = task::spawn_blocking(move || {
+            config.verify(&collection)?;
+
+            let file = match File::open(config.collection_path(&collection)) {
+                Ok(file) => file,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(error) => return Err(error.into()),
+            };
+
+            // SAFETY: the collection lock keeps this Database from writing to the file while
+            // it is mapped, and other writers are documented as unsupported.
+            let mmap = unsafe { memmap2::Mmap::map(&file)? };
+            let mut rdr = config
+                .reader_builder()
+                .from_reader(config.input(&mmap[..])?);
+            config.use_field_names(&mut rdr);
+
+            Ok(config.read_reader(&mut rdr, |headers, records| {
+                records
+                    .map(|record| config.deserialize(&*record?, headers))
+                    .filter(|document| config.keep_row(document))
+                    .collect()
+            })?)
+        })
+        .await;
+
+        Ok(results??.into_iter().filter(predicate).collect())
+    }
+
+    /// Find all documents on a collection as a stream, which yields them one at a time.
+    ///
+    /// The collection is read on a blocking thread that sends each document through a bounded
+    /// channel, so that reading never gets far ahead of the consumer of the stream.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    /// use tokio_stream::StreamExt;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_stream");
+    ///     let db = Database::new("data/find_stream", None);
+    ///
+    ///     let users = (1..=1000)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let users: Vec<User> = db
+    ///         .find_stream("users")
+    ///         .take(3)
+    ///         .collect::<Result<_, _>>()
+    ///         .await
+    ///         .expect("Problem streaming users.");
+    ///
+    ///     assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+    /// }
+    /// ```
+    pub fn find_stream<T>(&self, collection: &str) -> impl Stream<Item = Result<T, DbError>>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    {
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let cache = self.cache.clone();
+        let (tx, rx) = mpsc::channel(STREAM_CAPACITY);
+
+        task::spawn_blocking(move || {
+            let result = config.read_documents(&cache, &collection, (), |documents| {
+                for document in documents {
+                    let failed = document.is_err();
+
+                    if tx.blocking_send(document.map_err(DbError::from)).is_err() || failed {
+                        break;
+                    }
+                }
+
+                Ok(())
+            });
+
+            if let Err(error) = result {
+                let _ = tx.blocking_send(Err(error));
+            }
+        });
+
+        ReceiverStream::new(rx)
+    }
+
+    /// Watch a collection for changes to its file on disk, including those made by other
+    /// processes, as a stream that yields once per change.
+    ///
+    /// The directory of the collection is watched, and created if needed, so that a collection
+    /// that doesn't exist yet is reported once its file is created. The watch stops when the
+    /// stream is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use std::time::Duration;
+    /// use tokio_stream::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/watch");
+    ///     let db = Database::new("data/watch", None);
+    ///
+    ///     let mut changes = Box::pin(db.watch("users").expect("Problem watching users."));
+    ///
+    ///     std::fs::write("data/watch/users.csv", "id,first_name,last_name,age\n").unwrap();
+    ///
+    ///     let change = tokio::time::timeout(Duration::from_secs(5), changes.next())
+    ///         .await
+    ///         .expect("No change was reported.");
+    ///
+    ///     assert_eq!(change, Some(()));
+    /// }
+    /// ```
+    #[cfg(feature = "watch")]
+    pub fn watch(&self, collection: &str) -> Result<impl Stream<Item = ()>, DbError> {
+        use notify::{EventKind, RecursiveMode, Watcher};
+
+        let path = self.config.collection_path(collection);
+        let name = path.file_name().map(|name| name.to_os_string());
+        let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+
+        std::fs::create_dir_all(&dir)?;
+
+        let (tx, rx) = mpsc::channel(STREAM_CAPACITY);
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let changed = event.is_ok_and(|event| {
+                    matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    ) && event
+                        .paths
+                        .iter()
+                        .any(|path| path.file_name().map(|name| name.to_os_string()) == name)
+                });
+
+                // A full channel already has a change pending, so this one can be dropped.
+                if changed {
+                    let _ = tx.try_send(());
+                }
+            })?;
+
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+        // The watcher is kept alive together with the stream, which owns it.
+        Ok(futures::stream::unfold(
+            (watcher, rx),
+            |(watcher, mut rx)| async move { rx.recv().await.map(|()| ((), (watcher, rx))) },
+        ))
+    }
+
+    /// Find the first document matching a predicate on a collection.
+    ///
+    /// The collection is read only until a matching document is found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_one");
+    /// #   std::fs::create_dir_all("data/find_one").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/find_one/users.csv",
+    /// #       "id,first_name,last_name,age\n1,First,Last,20\n2,First,Last,21\ngarbage\n",
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = Database::new("data/find_one", None);
+    ///
+    ///     let user = db
+    ///         .find_one("users", |u: &User| u.id == 2)
+    ///         .await
+    ///         .expect("Problem searching user.");
+    ///
+    ///     assert_eq!(user.map(|u| u.age), Some(21));
+    /// }
+    /// ```
+    pub async fn find_one<T, P>(
+        &self,
+        collection: &str,
+        mut predicate: P,
+    ) -> Result<Option<T>, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool + Send + 'static,
+    {
+        self.read_documents(collection, None, move |documents| {
+            for document in documents {
+                let document = document?;
+
+                if predicate(&document) {
+                    return Ok(Some(document));
+                }
+            }
+
+            Ok(None)
+        })
+        .await
+    }
+
+    /// Find the first document matching a predicate on a collection, or the default document if
+    /// none matches, including when the collection doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+    /// struct Settings {
+    ///     name: String,
+    ///     theme: String,
+    ///     volume: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/get_or_default");
+    ///     let db = Database::new("data/get_or_default", None);
+    ///
+    ///     let settings: Settings = db
+    ///         .get_or_default("settings", |s: &Settings| s.name == "main")
+    ///         .await
+    ///         .expect("Problem searching settings.");
+    ///
+    ///     assert_eq!(settings, Settings::default());
+    ///
+    ///     let settings = Settings {
+    ///         name: String::from("main"),
+    ///         theme: String::from("dark"),
+    ///         volume: 7,
+    ///     };
+    ///
+    ///     db.insert("settings", settings)
+    ///         .await
+    ///         .expect("Problem inserting settings.");
+    ///
+    ///     let settings: Settings = db
+    ///         .get_or_default("settings", |s: &Settings| s.name == "main")
+    ///         .await
+    ///         .expect("Problem searching settings.");
+    ///
+    ///     assert_eq!(settings.theme, "dark");
+    ///     assert_eq!(settings.volume, 7);
+    ///
+    ///     let settings: Settings = db
+    ///         .get_or_default("settings", |s: &Settings| s.name == "other")
+    ///         .await
+    ///         .expect("Problem searching settings.");
+    ///
+    ///     assert_eq!(settings, Settings::default());
+    /// }
+    /// ```
+    pub async fn get_or_default<T, P>(&self, collection: &str, predicate: P) -> Result<T, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Default + Send + 'static,
+        P: FnMut(&T) -> bool + Send + 'static,
+    {
+        Ok(self
+            .find_one(collection, predicate)
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// Find the first document of a collection, reading only as far as it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/first");
+    ///     let db = Database::new("data/first", None);
+    ///
+    ///     let user: Option<User> = db.first("users").await.expect("Problem finding user.");
+    ///
+    ///     assert_eq!(user, None);
+    ///
+    ///     for id in 1..=3 {
+    ///         let user = User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         };
+    ///
+    ///         db.insert("users", user).await.expect("Problem inserting user.");
+    ///     }
+    ///
+    ///     let user: Option<User> = db.first("users").await.expect("Problem finding user.");
+    ///
+    ///     assert_eq!(user.map(|u| u.id), Some(1));
+    /// }
+    /// ```
+    pub async fn first<T>(&self, collection: &str) -> Result<Option<T>, DbError>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+    {
+        self.read_documents(collection, None, |documents| documents.next().transpose())
+            .await
+    }
+
+    /// Find the last document of a collection, without keeping the documents before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/last");
+    ///     let db = Database::new("data/last", None);
+    ///
+    ///     let user: Option<User> = db.last("users").await.expect("Problem finding user.");
+    ///
+    ///     assert_eq!(user, None);
+    ///
+    ///     for id in 1..=3 {
+    ///         let user = User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         };
+    ///
+    ///         db.insert("users", user).await.expect("Problem inserting user.");
+    ///     }
+    ///
+    ///     let user: Option<User> = db.last("users").await.expect("Problem finding user.");
+    ///
+    ///     assert_eq!(user.map(|u| u.id), Some(3));
+    /// }
+    /// ```
+    pub async fn last<T>(&self, collection: &str) -> Result<Option<T>, DbError>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+    {
+        self.read_documents(collection, None, |documents| {
+            let mut last = None;
+
+            for document in documents {
+                last = Some(document?);
+            }
+
+            Ok(last)
+        })
+        .await
+    }
+
+    /// Find up to n documents of a collection chosen uniformly at random, or all of them if the
+    /// collection has fewer than n documents.
+    ///
+    /// The documents are chosen with reservoir sampling, so that at most n documents are held in
+    /// memory at any time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/sample");
+    ///     let db = Database::new("data/sample", None);
+    ///
+    ///     let users = (0..100)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let sample: Vec<User> = db.sample("users", 10).await.expect("Problem sampling users.");
+    ///     let users: Vec<User> = db
+    ///         .find("users", |_| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(sample.len(), 10);
+    ///     assert!(sample.iter().all(|u| users.contains(u)));
+    ///
+    ///     let sample: Vec<User> = db.sample("users", 200).await.expect("Problem sampling users.");
+    ///
+    ///     assert_eq!(sample.len(), 100);
+    /// }
+    /// ```
+    pub async fn sample<T>(&self, collection: &str, n: usize) -> Result<Vec<T>, DbError>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+    {
+        self.read_documents(collection, Vec::new(), move |documents| {
+            let mut rng = rand::rng();
+            let mut reservoir = Vec::new();
+
+            for (index, document) in documents.enumerate() {
+                let document = document?;
+
+                if index < n {
+                    reservoir.push(document);
+                } else {
+                    let slot = rng.random_range(0..=index);
+
+                    if slot < n {
+                        reservoir[slot] = document;
+                    }
+                }
+            }
+
+            Ok(reservoir)
+        })
+        .await
+    }
+
+    /// Find the first document with the given id on a collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Database, Identifiable};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// impl Identifiable for User {
+    ///     type Id = usize;
+    ///
+    ///     fn id(&self) -> usize {
+    ///         self.id
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_by_id");
+    ///     let db = Database::new("data/find_by_id", None);
+    ///
+    ///     let users = (1..=3)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20 + id as u32,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let user: Option<User> = db
+    ///         .find_by_id("users", 2)
+    ///         .await
+    ///         .expect("Problem searching user.");
+    ///
+    ///     assert_eq!(user.map(|u| u.age), Some(22));
+    /// }
+    /// ```
+    pub async fn find_by_id<T>(&self, collection: &str, id: T::Id) -> Result<Option<T>, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Identifiable + Send + 'static,
+        T::Id: Send + 'static,
+    {
+        self.find_one(collection, move |d: &T| d.id() == id).await
+    }
+
+    /// Create an index on a collection that maps the key of each document to the position of its
+    /// record, returning how many documents were indexed.
+    ///
+    /// The index is stored in a `.idx` file next to the collection and used by
+    /// [`Database::find_by_indexed_key`]. It is tied to the current contents of the collection,
+    /// so it is removed whenever the collection is written, dropped or renamed, and must then be
+    /// created again. Indexes aren't supported on compressed collections.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/create_index");
+    ///     let db = Database::new("data/create_index", None);
+    ///
+    ///     let users = (1..=3)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let indexed = db
+    ///         .create_index("users", |u: &User| u.id)
+    ///         .await
+    ///         .expect("Problem indexing users.");
+    ///
+    ///     assert_eq!(indexed, 3);
+    ///     assert!(std::path::Path::new("data/create_index/users.csv.idx").exists());
+    ///
+    ///     db.drop_collection("users")
+    ///         .await
+    ///         .expect("Problem dropping users.");
+    ///
+    ///     assert!(!std::path::Path::new("data/create_index/users.csv.idx").exists());
+    /// }
+    /// ```
+    pub async fn create_index<T, K, F>(&self, collection: &str, key: F) -> Result<usize, DbError>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+        K: Serialize + Send + 'static,
+        F: Fn(&T) -> K + Send + 'static,
+    {
+        let _lock = self.lock(collection).await;
+
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let result: Result<Result<usize, DbError>, JoinError> = task::spawn_blocking(move || {
+            if config.compression != Compression::None {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "indexes aren't supported on compressed collections",
+                )
+                .into());
+            }
+
+            #[cfg(feature = "encryption")]
+            if config.encryption.is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "indexes aren't supported on encrypted collections",
+                )
+                .into());
+            }
+
+            let path = config.collection_path(&collection);
+
+            if !path.exists() {
+                return Err(DbError::NotFound { collection });
+            }
+
+            config.verify(&collection)?;
+
+            // Positions are taken from the raw file, so that they can be sought to directly.
+            let mut rdr = config.reader_builder().from_reader(File::open(&path)?);
+            let stamp = index_stamp(&path)?;
+            let mut record = StringRecord::new();
+            let headers = config.headers(&collection)?;
+
+            if config.has_headers {
+                rdr.headers()?;
+            }
+
+            let mut wrt = WriterBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_path(index_path(&path))?;
+            let mut indexed = 0;
+
+            wrt.write_record(&stamp)?;
+
+            loop {
+                let position = rdr.position().clone();
+
+                let document = match rdr.read_record(&mut record) {
+                    Ok(false) => break,
+                    Ok(true) => config.deserialize::<T>(&record, headers.as_ref()),
+                    Err(error) => Err(error),
+                };
+
+                // Skipped rows aren't indexed, as they can't be found as documents either.
+                if !config.keep_row(&document) {
+                    continue;
+                }
+
+                let document = document?;
+
+                wrt.serialize((
+                    key(&document),
+                    position.byte(),
+                    position.line(),
+                    position.record(),
+                ))?;
+                indexed += 1;
+            }
+
+            wrt.flush()?;
+
+            Ok(indexed)
+        })
+        .await;
+
+        result?
+    }
+
+    /// Find the first document with the given key on a collection, by seeking directly to its
+    /// record with the index created by [`Database::create_index`].
+    ///
+    /// A [`DbError::MissingIndex`] is returned if the collection has no index, or if it was
+    /// written since the index was created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Database, DbError};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_by_indexed_key");
+    ///     let db = Database::new("data/find_by_indexed_key", None);
+    ///
+    ///     let users = (1..=100)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: id as u32,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///     db.create_index("users", |u: &User| u.id)
+    ///         .await
+    ///         .expect("Problem indexing users.");
+    ///
+    ///     let user: Option<User> = db
+    ///         .find_by_indexed_key("users", &42)
+    ///         .await
+    ///         .expect("Problem searching user.");
+    ///
+    ///     assert_eq!(user.map(|u| u.age), Some(42));
+    ///
+    ///     let user: Option<User> = db
+    ///         .find_by_indexed_key("users", &1000)
+    ///         .await
+    ///         .expect("Problem searching user.");
+    ///
+    ///     assert_eq!(user, None);
+    ///
+    ///     db.delete("users", |u: &User| u.id == 42)
+    ///         .await
+    ///         .expect("Problem deleting user.");
+    ///
+    ///     match db.find_by_indexed_key::<User, _>("users", &42).await {
+    ///         Err(DbError::MissingIndex { .. }) => {}
+    ///         result => panic!("Expected a missing index, got {:?}.", result),
+    ///     }
+    /// }
+    /// ```
+    pub async fn find_by_indexed_key<T, K>(
+        &self,
+        collection: &str,
+        key: &K,
+    ) -> Result<Option<T>, DbError>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+        K: for<'de> Deserialize<'de> + PartialEq + Clone + Send + 'static,
+    {
+        let _lock = self.lock(collection).await;
+
+        let key = key.clone();
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let result: Result<Result<Option<T>, DbError>, JoinError> =
+            task::spawn_blocking(move || {
+                config.verify(&collection)?;
+
+                let path = config.collection_path(&collection);
+                let missing = || DbError::MissingIndex {
+                    collection: collection.clone(),
+                };
+
+                let mut index = match ReaderBuilder::new()
+                    .has_headers(false)
+                    .flexible(true)
+                    .from_path(index_path(&path))
+                {
+                    Ok(index) => index,
+                    Err(_) => return Err(missing()),
+                };
+
+                let mut stamp = StringRecord::new();
+
+                if !index.read_record(&mut stamp)? || index_stamp(&path).ok() != Some(stamp) {
+                    return Err(missing());
+                }
+
+                let mut found = None;
+
+                for entry in index.deserialize() {
+                    let (entry_key, byte, line, record): (K, u64, u64, u64) = entry?;
+
+                    if entry_key == key {
+                        let mut position = Position::new();
+                        position.set_byte(byte).set_line(line).set_record(record);
+                        found = Some(position);
+                        break;
+                    }
+                }
+
+                let position = match found {
+                    Some(position) => position,
+                    None => return Ok(None),
+                };
+
+                let mut rdr = config.reader_builder().from_reader(File::open(&path)?);
+                let mut record = StringRecord::new();
+                let headers = config.headers(&collection)?;
+
+                rdr.seek(position)?;
+
+                if !rdr.read_record(&mut record)? {
+                    return Ok(None);
+                }
+
+                Ok(Some(config.deserialize(&record, headers.as_ref())?))
+            })
+            .await;
+
+        result?
+    }
+
+    /// Check whether any document matching a predicate exists on a collection.
+    ///
+    /// The collection is read only until a matching document is found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/exists");
+    /// #   std::fs::create_dir_all("data/exists").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/exists/users.csv",
+    /// #       "id,first_name,last_name,age\n1,First,Last,20\ngarbage\n",
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = Database::new("data/exists", None);
+    ///
+    ///     let exists = db
+    ///         .exists("users", |u: &User| u.id == 1)
+    ///         .await
+    ///         .expect("Problem searching user.");
+    ///
+    ///     assert!(exists);
+    ///
+    ///     let exists = db
+    ///         .exists("absent", |u: &User| u.id == 1)
+    ///         .await
+    ///         .expect("Problem searching user.");
+    ///
+    ///     assert!(!exists);
+    /// }
+    /// ```
+    pub async fn exists<T, P>(&self, collection: &str, predicate: P) -> Result<bool, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool + Send + 'static,
+    {
+        Ok(self.find_one(collection, predicate).await?.is_some())
+    }
+
+    /// Find a page of documents by filtering with a predicate on a collection, skipping the first
+    /// `offset` matches and returning at most `limit` of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_paginated");
+    ///     let db = Database::new("data/find_paginated", None);
+    ///
+    ///     let users = (1..=5)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let page = db
+    ///         .find_paginated("users", |_: &User| true, 1, 2)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(page.iter().map(|u| u.id).collect::<Vec<_>>(), vec![2, 3]);
+    ///
+    ///     let page = db
+    ///         .find_paginated("users", |_: &User| true, 3, 10)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(page.iter().map(|u| u.id).collect::<Vec<_>>(), vec![4, 5]);
+    ///
+    ///     let page = db
+    ///         .find_paginated("users", |_: &User| true, 10, 2)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert!(page.is_empty());
+    /// }
+    /// ```
+    pub async fn find_paginated<T, P>(
+        &self,
+        collection: &str,
+        mut predicate: P,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<T>, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool + Send + 'static,
+    {
+        self.read_documents(collection, Vec::new(), move |documents| {
+            let mut page = Vec::new();
+            let mut skipped = 0;
+
+            for document in documents {
+                if page.len() >= limit {
+                    break;
+                }
+
+                let document = document?;
+
+                if !predicate(&document) {
+                    continue;
+                }
+
+                if skipped < offset {
+                    skipped += 1;
+                } else {
+                    page.push(document);
+                }
+            }
+
+            Ok(page)
+        })
+        .await
+    }
+
+    /// Find documents whose key, extracted by a function, lies within an inclusive range on a
+    /// collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_range");
+    ///     let db = Database::new("data/find_range", None);
+    ///
+    ///     let users = [17, 18, 25, 30, 31]
+    ///         .into_iter()
+    ///         .enumerate()
+    ///         .map(|(id, age)| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let users = db
+    ///         .find_range("users", |u: &User| u.age, 18, 30)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.iter().map(|u| u.age).collect::<Vec<_>>(), vec![18, 25, 30]);
+    /// }
+    /// ```
+    pub async fn find_range<T, K, F>(
+        &self,
+        collection: &str,
+        extract: F,
+        low: K,
+        high: K,
+    ) -> Result<Vec<T>, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        K: PartialOrd,
+        F: Fn(&T) -> K,
+    {
+        self.find(collection, |d: &T| {
+            let key = extract(d);
+
+            key >= low && key <= high
+        })
+        .await
+    }
+
+    /// Find documents by filtering with a predicate on a collection, sorted by a key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_sorted");
+    ///     let db = Database::new("data/find_sorted", None);
+    ///
+    ///     let users = [(1, 30), (2, 20), (3, 25)]
+    ///         .into_iter()
+    ///         .map(|(id, age)| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let users = db
+    ///         .find_sorted("users", |_: &User| true, |u: &User| u.age)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![2, 3, 1]);
+    /// }
+    /// ```
+    pub async fn find_sorted<T, P, F, K>(
+        &self,
+        collection: &str,
+        predicate: P,
+        key: F,
+    ) -> Result<Vec<T>, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool,
+        F: Fn(&T) -> K,
+        K: Ord,
+    {
+        let mut documents = self.find(collection, predicate).await?;
+
+        documents.sort_by_key(key);
+
+        Ok(documents)
+    }
+
+    /// Run a query on a collection, returning the documents it matches in the order it describes.
+    ///
+    /// See [`Query`] for an example.
+    pub async fn run<T>(&self, collection: &str, query: &Query<T>) -> Result<Vec<T>, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    {
+        let mut documents = self.find(collection, |d| query.matches(d)).await?;
+
+        query.arrange(&mut documents);
+
+        Ok(documents)
+    }
+
+    /// Find documents by filtering with a predicate on a collection, sorted with a comparator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_sorted_by");
+    ///     let db = Database::new("data/find_sorted_by", None);
+    ///
+    ///     let users = [(1, 30), (2, 20), (3, 25)]
+    ///         .into_iter()
+    ///         .map(|(id, age)| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let users = db
+    ///         .find_sorted_by("users", |_: &User| true, |a: &User, b: &User| b.age.cmp(&a.age))
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![1, 3, 2]);
+    /// }
+    /// ```
+    pub async fn find_sorted_by<T, P, F>(
+        &self,
+        collection: &str,
+        predicate: P,
+        compare: F,
+    ) -> Result<Vec<T>, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool,
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut documents = self.find(collection, predicate).await?;
+
+        documents.sort_by(compare);
+
+        Ok(documents)
+    }
+
+    /// Find the n documents with the greatest keys on a collection, sorted by descending key, with
+    /// ties kept in collection order.
+    ///
+    /// Only the best n documents seen so far are kept in memory while the collection is read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/top_n");
+    ///     let db = Database::new("data/top_n", None);
+    ///
+    ///     let users = (0..50)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: ((id * 37) % 50) as u32,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let oldest = db
+    ///         .top_n("users", 3, |u: &User| u.age)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(oldest.iter().map(|u| u.age).collect::<Vec<_>>(), vec![49, 48, 47]);
+    /// }
+    /// ```
+    pub async fn top_n<T, K, F>(
+        &self,
+        collection: &str,
+        n: usize,
+        key: F,
+    ) -> Result<Vec<T>, DbError>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+        K: Ord + Send + 'static,
+        F: Fn(&T) -> K + Send + 'static,
+    {
+        self.read_documents(collection, Vec::new(), move |documents| {
+            if n == 0 {
+                return Ok(Vec::new());
+            }
+
+            let mut heap: BinaryHeap<Reverse<Ranked<K, T>>> = BinaryHeap::new();
+
+            for (position, document) in documents.enumerate() {
+                let document = document?;
+                let ranked = Ranked {
+                    key: key(&document),
+                    position,
+                    document,
+                };
+
+                if heap.len() < n {
+                    heap.push(Reverse(ranked));
+                } else if heap.peek().is_some_and(|least| ranked > least.0) {
+                    heap.pop();
+                    heap.push(Reverse(ranked));
+                }
+            }
+
+            Ok(heap
+                .into_sorted_vec()
+                .into_iter()
+                .map(|Reverse(ranked)| ranked.document)
+                .collect())
+        })
+        .await
+    }
+
+    /// Count the documents in a collection, without deserializing them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/count");
+    ///     let db = Database::new("data/count", None);
+    ///
+    ///     for id in 1..=3 {
+    ///         let user = User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         };
+    ///
+    ///         db.insert("users", user)
+    ///             .await
+    ///             .expect("Problem inserting user.");
+    ///     }
+    ///
+    ///     assert_eq!(db.count("users").await.expect("Problem counting users."), 3);
+    ///     assert_eq!(db.count("absent").await.expect("Problem counting users."), 0);
+    /// }
+    /// ```
+    pub async fn count(&self, collection: &str) -> Result<usize, DbError> {
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let cache = self.cache.clone();
+        let result: Result<Result<usize, DbError>, JoinError> = task::spawn_blocking(move || {
+            config.read_records(&cache, &collection, 0, |_, records| {
+                let mut count = 0;
+
+                for record in records {
+                    record?;
+                    count += 1;
+                }
+
+                Ok(count)
+            })
+        })
+        .await;
+
+        result?
+    }
+
+    /// Count the documents in a collection as fast as possible, by reusing a single raw byte
+    /// record for every row.
+    ///
+    /// The count is exact: rows are delimited by the same parser used for reading documents, so
+    /// quoted fields with embedded newlines are counted as part of a single document, and the
+    /// header row isn't counted. Unlike [`Database::count`], fields aren't validated as UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/count_fast");
+    ///     let db = Database::new("data/count_fast", None);
+    ///
+    ///     let users = ["Last", "Multi\nLine"]
+    ///         .into_iter()
+    ///         .enumerate()
+    ///         .map(|(id, last_name)| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from(last_name),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     assert_eq!(db.count_fast("users").await.expect("Problem counting users."), 2);
+    /// }
+    /// ```
+    pub async fn count_fast(&self, collection: &str) -> Result<usize, DbError> {
+        self.read(collection, 0, |rdr| {
+            let mut record = ByteRecord::new();
+            let mut count = 0;
+
+            while rdr.read_byte_record(&mut record)? {
+                count += 1;
+            }
+
+            Ok(count)
+        })
+        .await
+    }
+
+    /// Count the documents matching a predicate on a collection, without collecting them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/count_where");
+    ///     let db = Database::new("data/count_where", None);
+    ///
+    ///     let users = [10, 15, 18, 30, 45]
+    ///         .into_iter()
+    ///         .enumerate()
+    ///         .map(|(id, age)| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let adults = db
+    ///         .count_where("users", |u: &User| u.age >= 18)
+    ///         .await
+    ///         .expect("Problem counting users.");
+    ///
+    ///     assert_eq!(adults, 3);
+    /// }
+    /// ```
+    pub async fn count_where<T, P>(
+        &self,
+        collection: &str,
+        mut predicate: P,
+    ) -> Result<usize, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool + Send + 'static,
+    {
+        self.read_documents(collection, 0, move |documents| {
+            let mut count = 0;
+
+            for document in documents {
+                if predicate(&document?) {
+                    count += 1;
+                }
+            }
+
+            Ok(count)
+        })
+        .await
+    }
+
+    /// Aggregate a numeric field over the documents matching a predicate on a collection, in a
+    /// single pass over its file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/aggregate");
+    ///     let db = Database::new("data/aggregate", None);
+    ///
+    ///     let users = [20, 30, 40]
+    ///         .into_iter()
+    ///         .enumerate()
+    ///         .map(|(id, age)| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let ages = db
+    ///         .aggregate("users", |_: &User| true, |u: &User| u.age as f64)
+    ///         .await
+    ///         .expect("Problem aggregating users.");
+    ///
+    ///     assert_eq!(ages.count, 3);
+    ///     assert_eq!(ages.sum, 90.0);
+    ///     assert_eq!(ages.min, Some(20.0));
+    ///     assert_eq!(ages.max, Some(40.0));
+    ///     assert_eq!(ages.avg, Some(30.0));
+    /// }
+    /// ```
+    pub async fn aggregate<T, P, F>(
+        &self,
+        collection: &str,
+        mut predicate: P,
+        extract: F,
+    ) -> Result<Aggregate, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool + Send + 'static,
+        F: Fn(&T) -> f64 + Send + 'static,
+    {
+        let empty = Aggregate {
+            count: 0,
+            sum: 0.0,
+            min: None,
+            max: None,
+            avg: None,
+        };
+
+        self.read_documents(collection, empty, move |documents| {
+            let mut aggregate = empty;
+
+            for document in documents {
+                let document = document?;
+
+                if !predicate(&document) {
+                    continue;
+                }
+
+                let value = extract(&document);
+
+                aggregate.count += 1;
+                aggregate.sum += value;
+                aggregate.min = Some(aggregate.min.map_or(value, |min| min.min(value)));
+                aggregate.max = Some(aggregate.max.map_or(value, |max| max.max(value)));
+            }
+
+            if aggregate.count > 0 {
+                aggregate.avg = Some(aggregate.sum / aggregate.count as f64);
+            }
+
+            Ok(aggregate)
+        })
+        .await
+    }
+
+    /// Call a function with each document of a collection, reading them one at a time.
+    ///
+    /// The function runs on the blocking thread that reads the collection, so that documents are
+    /// handed over as they are read without being collected. That's why it must be `Send` and
+    /// `'static`, sharing any state it updates through types such as `Arc`, and why it should
+    /// avoid blocking for long.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    /// use std::sync::{
+    ///     atomic::{AtomicU32, Ordering},
+    ///     Arc,
+    /// };
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/for_each");
+    ///     let db = Database::new("data/for_each", None);
+    ///
+    ///     let users = (1..=4)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 10 * id as u32,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let total = Arc::new(AtomicU32::new(0));
+    ///     let counter = total.clone();
+    ///
+    ///     db.for_each("users", move |u: User| {
+    ///         counter.fetch_add(u.age, Ordering::Relaxed);
+    ///     })
+    ///     .await
+    ///     .expect("Problem iterating users.");
+    ///
+    ///     assert_eq!(total.load(Ordering::Relaxed), 100);
+    /// }
+    /// ```
+    pub async fn for_each<T, F>(&self, collection: &str, mut f: F) -> Result<(), DbError>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+        F: FnMut(T) + Send + 'static,
+    {
+        self.read_documents(collection, (), move |documents| {
+            for document in documents {
+                f(document?);
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Find the value of a field across all documents of a collection, in the order of the
+    /// collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/pluck");
+    ///     let db = Database::new("data/pluck", None);
+    ///
+    ///     let users = [3, 1, 2]
+    ///         .into_iter()
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let ids = db
+    ///         .pluck("users", |u: &User| u.id)
+    ///         .await
+    ///         .expect("Problem plucking ids.");
+    ///
+    ///     assert_eq!(ids, vec![3, 1, 2]);
+    /// }
+    /// ```
+    pub async fn pluck<T, K, F>(&self, collection: &str, extract: F) -> Result<Vec<K>, DbError>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+        K: Send + 'static,
+        F: Fn(&T) -> K + Send + 'static,
+    {
+        self.read_documents(collection, Vec::new(), move |documents| {
+            documents
+                .map(|document| document.map(|document| extract(&document)))
+                .collect()
+        })
+        .await
+    }
+
+    /// Find the distinct values of a field across the documents of a collection, in the order
+    /// they're first seen.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/distinct");
+    ///     let db = Database::new("data/distinct", None);
+    ///
+    ///     let users = ["Smith", "Doe", "Smith", "Brown", "Doe"]
+    ///         .into_iter()
+    ///         .enumerate()
+    ///         .map(|(id, last_name)| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from(last_name),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let last_names = db
+    ///         .distinct("users", |u: &User| u.last_name.clone())
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(last_names, vec!["Smith", "Doe", "Brown"]);
+    /// }
+    /// ```
+    pub async fn distinct<T, K, F>(&self, collection: &str, extract: F) -> Result<Vec<K>, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        K: Eq + Hash + Clone + Send + 'static,
+        F: Fn(&T) -> K + Send + 'static,
+    {
+        self.read_documents(collection, Vec::new(), move |documents| {
+            let mut seen = HashSet::new();
+            let mut values = Vec::new();
+
+            for document in documents {
+                let value = extract(&document?);
+
+                if seen.insert(value.clone()) {
+                    values.push(value);
+                }
+            }
+
+            Ok(values)
+        })
+        .await
+    }
+
+    /// Group the documents of a collection into buckets by a key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/group_by");
+    ///     let db = Database::new("data/group_by", None);
+    ///
+    ///     let users = [21, 25, 34, 47, 42, 40]
+    ///         .into_iter()
+    ///         .enumerate()
+    ///         .map(|(id, age)| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let decades = db
+    ///         .group_by("users", |u: &User| u.age / 10)
+    ///         .await
+    ///         .expect("Problem grouping users.");
+    ///
+    ///     assert_eq!(decades.len(), 3);
+    ///     assert_eq!(decades[&2].len(), 2);
+    ///     assert_eq!(decades[&3].len(), 1);
+    ///     assert_eq!(decades[&4].len(), 3);
+    /// }
+    /// ```
+    pub async fn group_by<T, K, F>(
+        &self,
+        collection: &str,
+        key: F,
+    ) -> Result<HashMap<K, Vec<T>>, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        K: Eq + Hash + Send + 'static,
+        F: Fn(&T) -> K + Send + 'static,
+    {
+        self.read_documents(collection, HashMap::new(), move |documents| {
+            let mut groups: HashMap<K, Vec<T>> = HashMap::new();
+
+            for document in documents {
+                let document = document?;
+
+                groups.entry(key(&document)).or_default().push(document);
+            }
+
+            Ok(groups)
+        })
+        .await
+    }
+
+    /// Count the documents of a collection by a key, without keeping the documents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/count_by");
+    ///     let db = Database::new("data/count_by", None);
+    ///
+    ///     let users = [21, 25, 34, 47, 42, 40]
+    ///         .into_iter()
+    ///         .enumerate()
+    ///         .map(|(id, age)| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let decades = db
+    ///         .count_by("users", |u: &User| u.age / 10)
+    ///         .await
+    ///         .expect("Problem counting users.");
+    ///
+    ///     assert_eq!(decades.len(), 3);
+    ///     assert_eq!(decades[&2], 2);
+    ///     assert_eq!(decades[&3], 1);
+    ///     assert_eq!(decades[&4], 3);
+    /// }
+    /// ```
+    pub async fn count_by<T, K, F>(
+        &self,
+        collection: &str,
+        key: F,
+    ) -> Result<HashMap<K, usize>, DbError>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+        K: Eq + Hash + Send + 'static,
+        F: Fn(&T) -> K + Send + 'static,
+    {
+        self.read_documents(collection, HashMap::new(), move |documents| {
+            let mut counts: HashMap<K, usize> = HashMap::new();
+
+            for document in documents {
+                *counts.entry(key(&document?)).or_default() += 1;
+            }
+
+            Ok(counts)
+        })
+        .await
+    }
+
+    /// Join two collections on a key, returning a pair for each left and right document with equal
+    /// keys, in the order of the left collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    /// struct Order {
+    ///     id: usize,
+    ///     user_id: usize,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/join");
+    ///     let db = Database::new("data/join", None);
+    ///
+    ///     let users = (1..=3)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///     let orders = vec![
+    ///         Order { id: 1, user_id: 2 },
+    ///         Order { id: 2, user_id: 1 },
+    ///         Order { id: 3, user_id: 2 },
+    ///     ];
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///     db.insert_many("orders", orders)
+    ///         .await
+    ///         .expect("Problem inserting orders.");
+    ///
+    ///     let pairs = db
+    ///         .join("users", "orders", |u: &User| u.id, |o: &Order| o.user_id)
+    ///         .await
+    ///         .expect("Problem joining users and orders.");
+    ///
+    ///     assert_eq!(
+    ///         pairs.iter().map(|(u, o)| (u.id, o.id)).collect::<Vec<_>>(),
+    ///         vec![(1, 2), (2, 1), (2, 3)]
+    ///     );
+    /// }
+    /// ```
+    pub async fn join<L, R, K, FL, FR>(
+        &self,
+        left: &str,
+        right: &str,
+        left_key: FL,
+        right_key: FR,
+    ) -> Result<Vec<(L, R)>, DbError>
+    where
+        L: Serialize + for<'de> Deserialize<'de> + Clone + Send + 'static,
+        R: Serialize + for<'de> Deserialize<'de> + Clone + Send + 'static,
+        K: Eq + Hash + Send + 'static,
+        FL: Fn(&L) -> K,
+        FR: Fn(&R) -> K + Send + 'static,
+    {
+        let rights = self.group_by(right, right_key).await?;
+        let lefts: Vec<L> = self.find(left, |_| true).await?;
+        let mut pairs = Vec::new();
+
+        for left in lefts {
+            if let Some(rights) = rights.get(&left_key(&left)) {
+                for right in rights {
+                    pairs.push((left.clone(), right.clone()));
+                }
+            }
+        }
+
+        Ok(pairs)
+    }
+
+    /// Insert a new document into a collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let db = Database::new("data", None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user)
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    /// }
+    /// ```
+    ///
+    /// Concurrent inserts into the same collection never lose each other's documents:
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    /// use std::sync::Arc;
+    /// use tokio::task::JoinSet;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/insert_concurrent");
+    ///     let db = Arc::new(Database::new("data/insert_concurrent", None));
+    ///     let mut tasks = JoinSet::new();
+    ///
+    ///     for id in 1..=50 {
+    ///         let db = db.clone();
+    ///
+    ///         tasks.spawn(async move {
+    ///             let user = User {
+    ///                 id,
+    ///                 first_name: String::from("First"),
+    ///                 last_name: String::from("Last"),
+    ///                 age: 20,
+    ///             };
+    ///
+    ///             db.insert("users", user)
+    ///                 .await
+    ///                 .expect("Problem inserting user.");
+    ///         });
+    ///     }
+    ///
+    ///     while let Some(result) = tasks.join_next().await {
+    ///         result.expect("Problem joining task.");
+    ///     }
+    ///
+    ///     assert_eq!(db.count("users").await.expect("Problem counting users."), 50);
+    /// }
+    /// ```
+    pub async fn insert<T>(&self, collection: &str, document: T) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    {
+        let _lock = self.lock(collection).await;
+        let mut documents: Vec<T> = self.find(collection, |_| true).await?;
+
+        documents.push(document);
+
+        Ok(self.write(collection, documents).await??)
+    }
+
+    /// Insert many new documents into a collection, rewriting the file only once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/insert_many");
+    ///     let db = Database::new("data/insert_many", None);
+    ///
+    ///     let users = (1..=1000)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 1000);
+    /// }
+    /// ```
+    pub async fn insert_many<T>(&self, collection: &str, documents: Vec<T>) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    {
+        let _lock = self.lock(collection).await;
+        let mut existing: Vec<T> = self.find(collection, |_| true).await?;
+
+        existing.extend(documents);
+
+        Ok(self.write(collection, existing).await??)
+    }
+
+    /// Insert a new document with an automatically generated id into a collection, returning the
+    /// generated id.
+    ///
+    /// The id is one greater than the largest id in the collection, or 1 for an empty collection,
+    /// and is handed to the closure that makes the document. Existing ids are read from the `id`
+    /// column, or from the first column when the collection has no header row. Ids that aren't
+    /// numbers are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: u64,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/insert_auto_id");
+    ///     let db = Database::new("data/insert_auto_id", None);
+    ///
+    ///     for expected in 1..=3 {
+    ///         let id = db
+    ///             .insert_auto_id("users", |id| User {
+    ///                 id,
+    ///                 first_name: String::from("First"),
+    ///                 last_name: String::from("Last"),
+    ///                 age: 20,
+    ///             })
+    ///             .await
+    ///             .expect("Problem inserting user.");
+    ///
+    ///         assert_eq!(id, expected);
+    ///     }
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+    /// }
+    /// ```
+    pub async fn insert_auto_id<T, F>(&self, collection: &str, make: F) -> Result<u64, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        F: FnOnce(u64) -> T,
+    {
+        let _lock = self.lock(collection).await;
+
+        let name = collection.to_string();
+        let config = self.config.clone();
+        let cache = self.cache.clone();
+        let result: Result<Result<(Vec<T>, u64), DbError>, JoinError> =
+            task::spawn_blocking(move || {
+                config.read_records(&cache, &name, (Vec::new(), 0), |headers, records| {
+                    let column =
+                        headers.map_or(Some(0), |headers| headers.iter().position(|h| h == "id"));
+
+                    let mut documents = Vec::new();
+                    let mut max_id = 0;
+
+                    for record in records {
+                        let record = record?;
+
+                        if let Some(id) = column
+                            .and_then(|column| record.get(column))
+                            .and_then(|id| id.trim().parse::<u64>().ok())
+                        {
+                            max_id = max_id.max(id);
+                        }
+
+                        let document = config.deserialize(&record, headers);
+
+                        if config.keep_row(&document) {
+                            documents.push(document?);
+                        }
+                    }
+
+                    Ok((documents, max_id))
+                })
+            })
+            .await;
+
+        let (mut documents, max_id) = result??;
+        let id = max_id + 1;
+
+        documents.push(make(id));
+
+        self.write(collection, documents).await??;
+
+        Ok(id)
+    }
+
+    /// Insert a new document into a collection, unless an existing document has the same key.
+    ///
+    /// Returns [`DbError::DuplicateKey`] if any existing document has the same key as the new one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Database, DbError};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/insert_unique");
+    ///     let db = Database::new("data/insert_unique", None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert_unique("users", user, |u: &User| u.id)
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("Other"),
+    ///         last_name: String::from("Last"),
+    ///         age: 30,
+    ///     };
+    ///
+    ///     assert!(matches!(
+    ///         db.insert_unique("users", user, |u: &User| u.id).await,
+    ///         Err(DbError::DuplicateKey { .. })
+    ///     ));
+    ///     assert_eq!(db.count("users").await.expect("Problem counting users."), 1);
+    /// }
+    /// ```
+    pub async fn insert_unique<T, K, F>(
+        &self,
+        collection: &str,
+        document: T,
+        key: F,
+    ) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        K: PartialEq,
+        F: Fn(&T) -> K,
+    {
+        let _lock = self.lock(collection).await;
+        let mut documents: Vec<T> = self.find(collection, |_| true).await?;
+
+        let document_key = key(&document);
+
+        if documents.iter().any(|d| key(d) == document_key) {
+            return Err(DbError::DuplicateKey {
+                collection: collection.to_string(),
+            });
+        }
+
+        documents.push(document);
+
+        Ok(self.write(collection, documents).await??)
+    }
+
+    /// Insert a new document into a collection only if no existing document matches a predicate,
+    /// returning whether the document was inserted. Existing documents are never modified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/insert_if_not_exists");
+    ///     let db = Database::new("data/insert_if_not_exists", None);
+    ///
+    ///     for (age, expected) in [(20, true), (30, false)] {
+    ///         let user = User {
+    ///             id: 1,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age,
+    ///         };
+    ///
+    ///         let inserted = db
+    ///             .insert_if_not_exists("users", user, |u: &User| u.id == 1)
+    ///             .await
+    ///             .expect("Problem inserting user.");
+    ///
+    ///         assert_eq!(inserted, expected);
+    ///     }
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 1);
+    ///     assert_eq!(users[0].age, 20);
+    /// }
+    /// ```
+    pub async fn insert_if_not_exists<T, P>(
+        &self,
+        collection: &str,
+        document: T,
+        predicate: P,
+    ) -> Result<bool, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        P: FnMut(&T) -> bool,
+    {
+        let _lock = self.lock(collection).await;
+        let mut documents: Vec<T> = self.find(collection, |_| true).await?;
+
+        if documents.iter().any(predicate) {
+            return Ok(false);
+        }
+
+        documents.push(document);
+
+        self.write(collection, documents).await??;
+
+        Ok(true)
+    }
+
+    /// Find the first document matching a predicate on a collection, or insert the document made
+    /// by the closure when none matches, returning either of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_or_insert");
+    ///     let db = Database::new("data/find_or_insert", None);
+    ///
+    ///     for age in [20, 30] {
+    ///         let user = db
+    ///             .find_or_insert("users", |u: &User| u.id == 1, || User {
+    ///                 id: 1,
+    ///                 first_name: String::from("First"),
+    ///                 last_name: String::from("Last"),
+    ///                 age,
+    ///             })
+    ///             .await
+    ///             .expect("Problem finding or inserting user.");
+    ///
+    ///         assert_eq!(user.age, 20);
+    ///     }
+    ///
+    ///     assert_eq!(db.count("users").await.expect("Problem counting users."), 1);
+    /// }
+    /// ```
+    pub async fn find_or_insert<T, P, F>(
+        &self,
+        collection: &str,
+        predicate: P,
+        default: F,
+    ) -> Result<T, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Clone + Send + 'static,
+        P: FnMut(&T) -> bool,
+        F: FnOnce() -> T,
+    {
+        let _lock = self.lock(collection).await;
+        let mut documents: Vec<T> = self.find(collection, |_| true).await?;
+
+        if let Some(index) = documents.iter().position(predicate) {
+            return Ok(documents.swap_remove(index));
+        }
+
+        let document = default();
+
+        documents.push(document.clone());
+
+        self.write(collection, documents).await??;
+
+        Ok(document)
+    }
+
+    /// Insert a new document into a collection, after checking it with a validator.
+    ///
+    /// Returns [`DbError::Validation`] with the message of the validator if the document is
+    /// rejected, in which case nothing is written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Database, DbError};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// fn validate(user: &User) -> Result<(), String> {
+    ///     match user.age {
+    ///         0..=150 => Ok(()),
+    ///         age => Err(format!("age out of range: {}", age)),
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/insert_validated");
+    ///     let db = Database::new("data/insert_validated", None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 200,
+    ///     };
+    ///
+    ///     assert!(matches!(
+    ///         db.insert_validated("users", user, validate).await,
+    ///         Err(DbError::Validation(_))
+    ///     ));
+    ///     assert_eq!(db.count("users").await.expect("Problem counting users."), 0);
+    /// }
+    /// ```
+    pub async fn insert_validated<T, V>(
+        &self,
+        collection: &str,
+        document: T,
+        validator: V,
+    ) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        V: Fn(&T) -> Result<(), String>,
+    {
+        validator(&document).map_err(DbError::Validation)?;
+
+        self.insert(collection, document).await
+    }
+
+    /// Insert a new document into a collection, after setting both its creation and update
+    /// timestamps to the current time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Database, Timestamped};
+    /// use serde::{Deserialize, Serialize};
+    /// use std::time::{SystemTime, UNIX_EPOCH};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    ///     created_at: u128,
+    ///     updated_at: u128,
+    /// }
+    ///
+    /// impl Timestamped for User {
+    ///     fn set_created_at(&mut self, time: SystemTime) {
+    ///         self.created_at = time.duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    ///     }
+    ///
+    ///     fn set_updated_at(&mut self, time: SystemTime) {
+    ///         self.updated_at = time.duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/insert_timestamped");
+    ///     let db = Database::new("data/insert_timestamped", None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///         created_at: 0,
+    ///         updated_at: 0,
+    ///     };
+    ///
+    ///     db.insert_timestamped("users", user)
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     let users = db
+    ///         .find("users", |u: &User| u.id == 1)
+    ///         .await
+    ///         .expect("Problem searching user.");
+    ///
+    ///     assert!(users[0].created_at > 0);
+    ///     assert_eq!(users[0].created_at, users[0].updated_at);
+    /// }
+    /// ```
+    pub async fn insert_timestamped<T>(
+        &self,
+        collection: &str,
+        mut document: T,
+    ) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Timestamped + Send + 'static,
+    {
+        let now = SystemTime::now();
+
+        document.set_created_at(now);
+        document.set_updated_at(now);
+
+        self.insert(collection, document).await
+    }
+
+    /// Append a new document to the end of a collection, without reading or rewriting the file.
+    ///
+    /// The header row is only written when the collection file is newly created or empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/append");
+    ///     let db = Database::new("data/append", None);
+    ///
+    ///     for id in 1..=2 {
+    ///         let user = User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         };
+    ///
+    ///         db.append("users", user)
+    ///             .await
+    ///             .expect("Problem appending user.");
+    ///     }
+    ///
+    ///     let contents = std::fs::read_to_string("data/append/users.csv").unwrap();
+    ///
+    ///     assert_eq!(contents.matches("first_name").count(), 1);
+    ///     assert_eq!(contents.lines().count(), 3);
+    /// }
+    /// ```
+    pub async fn append<T>(&self, collection: &str, document: T) -> Result<(), DbError>
+    where
+        T: Serialize + Send + 'static,
+    {
+        let _lock = self.lock(collection).await;
+        self.invalidate(collection);
+
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let result: Result<Result<(), DbError>, JoinError> = task::spawn_blocking(move || {
+            config.verify_checksum(&collection)?;
+
+            let (mut wrt, empty) = config.appender(&collection)?;
+
+            if empty {
+                config.write_renamed_header(&mut wrt, &document)?;
+            }
+
+            config.serialize(&mut wrt, &document)?;
+            wrt.flush()?;
+            wrt.into_inner().map_err(|e| e.into_error())?.finish()?;
+
+            Ok(config.store_checksum(&config.collection_path(&collection))?)
+        })
+        .await;
+
+        result?
+    }
+
+    /// Insert the documents yielded by a stream at the end of a collection as they arrive,
+    /// returning how many documents were inserted.
+    ///
+    /// The stream is driven on the async side, which forwards batches of documents to a blocking
+    /// writer through a bounded channel, so that the whole stream is never held in memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/insert_stream");
+    ///     let db = Database::new("data/insert_stream", None);
+    ///
+    ///     let users = futures::stream::iter((1..=100).map(|id| User {
+    ///         id,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     }));
+    ///
+    ///     let inserted = db
+    ///         .insert_stream("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///     let contents = std::fs::read_to_string("data/insert_stream/users.csv").unwrap();
+    ///
+    ///     assert_eq!(inserted, 100);
+    ///     assert_eq!(users.len(), 100);
+    ///     assert_eq!(contents.lines().count(), 101);
+    /// }
+    /// ```
+    pub async fn insert_stream<T, S>(&self, collection: &str, stream: S) -> Result<usize, DbError>
+    where
+        T: Serialize + Send + 'static,
+        S: Stream<Item = T>,
+    {
+        let _lock = self.lock(collection).await;
+        self.invalidate(collection);
+
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let (tx, mut rx) = mpsc::channel::<Vec<T>>(STREAM_CAPACITY);
+        let writer = task::spawn_blocking(move || -> Result<usize, DbError> {
+            config.verify_checksum(&collection)?;
+
+            let (mut wrt, empty) = config.appender(&collection)?;
+            let mut inserted = 0;
+
+            while let Some(documents) = rx.blocking_recv() {
+                for document in documents {
+                    if empty && inserted == 0 {
+                        config.write_renamed_header(&mut wrt, &document)?;
+                    }
+
+                    config.serialize(&mut wrt, &document)?;
+                    inserted += 1;
+                }
+            }
+
+            wrt.flush()?;
+            wrt.into_inner().map_err(|e| e.into_error())?.finish()?;
+            config.store_checksum(&config.collection_path(&collection))?;
+
+            Ok(inserted)
+        });
+
+        let mut batches = std::pin::pin!(stream.ready_chunks(STREAM_CAPACITY));
+
+        while let Some(documents) = batches.next().await {
+            // The writer only stops receiving when it fails, which is reported below.
+            if tx.send(documents).await.is_err() {
+                break;
+            }
+        }
+
+        drop(tx);
+
+        writer.await?
+    }
+
+    /// Delete a document by filtering with a predicate from a collection, returning how many
+    /// documents were deleted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/delete");
+    ///     let db = Database::new("data/delete", None);
+    ///
+    ///     let users = (1..=5)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     // The same predicate can be used to find and delete documents.
+    ///     let predicate = |u: &User| u.id <= 2;
+    ///
+    ///     let found = db
+    ///         .find("users", predicate)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///     let deleted = db
+    ///         .delete("users", predicate)
+    ///         .await
+    ///         .expect("Problem deleting users.");
+    ///
+    ///     assert_eq!(deleted, found.len());
+    ///     assert_eq!(deleted, 2);
+    ///
+    ///     let deleted = db
+    ///         .delete("empty", |u: &User| u.id <= 2)
+    ///         .await
+    ///         .expect("Problem deleting users.");
+    ///
+    ///     assert_eq!(deleted, 0);
+    /// }
+    /// ```
+    pub async fn delete<T, P>(&self, collection: &str, predicate: P) -> Result<usize, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + Send + 'static,
+        P: FnMut(&T) -> bool,
+    {
+        let _lock = self.lock(collection).await;
+        let mut documents: Vec<T> = self.find(collection, |_| true).await?;
+
+        let deleted = remove_matching(&mut documents, predicate);
+
+        self.write(collection, documents).await??;
+
+        Ok(deleted)
+    }
+
+    /// Delete all documents whose key is among the given ids from a collection, rewriting the
+    /// file only once and returning how many documents were deleted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: u64,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/delete_by_ids");
+    ///     let db = Database::new("data/delete_by_ids", None);
+    ///
+    ///     let users = (1..=10)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let deleted = db
+    ///         .delete_by_ids("users", &[2, 5, 7], |u: &User| u.id)
+    ///         .await
+    ///         .expect("Problem deleting users.");
+    ///
+    ///     assert_eq!(deleted, 3);
+    ///     assert_eq!(db.count("users").await.expect("Problem counting users."), 7);
+    /// }
+    /// ```
+    pub async fn delete_by_ids<T, K, F>(
+        &self,
+        collection: &str,
+        ids: &[K],
+        key: F,
+    ) -> Result<usize, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + Send + 'static,
+        K: Eq + Hash,
+        F: Fn(&T) -> K,
+    {
+        let ids: HashSet<&K> = ids.iter().collect();
+
+        self.delete(collection, |d: &T| ids.contains(&key(d))).await
+    }
+
+    /// Remove exact duplicate documents from a collection, keeping the first occurrence of each and
+    /// returning how many documents were removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/dedupe");
+    ///     let db = Database::new("data/dedupe", None);
+    ///
+    ///     let users = [2, 1, 2]
+    ///         .into_iter()
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let removed = db
+    ///         .dedupe::<User>("users")
+    ///         .await
+    ///         .expect("Problem deduplicating users.");
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(removed, 1);
+    ///     assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![2, 1]);
+    /// }
+    /// ```
+    pub async fn dedupe<T>(&self, collection: &str) -> Result<usize, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + Send + 'static,
+    {
+        let _lock = self.lock(collection).await;
+        let documents: Vec<T> = self.find(collection, |_| true).await?;
+        let len = documents.len();
+
+        let mut unique: Vec<T> = Vec::with_capacity(len);
+
+        for document in documents {
+            if !unique.contains(&document) {
+                unique.push(document);
+            }
+        }
+
+        let removed = len - unique.len();
+
+        self.write(collection, unique).await??;
+
+        Ok(removed)
+    }
+
+    /// Update a document by filtering with a predicate on a collection.
+    ///
+    /// The first matching document is replaced in place, keeping its position in the collection,
+    /// and any other matching documents are removed. If no document matches, the new document is
+    /// inserted at the end of the collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/update");
+    ///     let db = Database::new("data/update", None);
+    ///
+    ///     let users = (1..=5)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let user = User {
+    ///         id: 3,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 21,
+    ///     };
+    ///
+    ///     db.update("users", user, |u: &User| u.id == 3)
+    ///         .await
+    ///         .expect("Problem updating user.");
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+    ///     assert_eq!(users[2].age, 21);
+    /// }
+    /// ```
+    ///
+    /// The collection is rewritten atomically, so if a document fails to serialize, the file is
+    /// left unchanged and no temporary file is left behind:
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{ser::Error, Deserialize, Serialize, Serializer};
+    ///
+    /// fn checked_age<S: Serializer>(age: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+    ///     if *age > 150 {
+    ///         return Err(S::Error::custom("age out of range"));
+    ///     }
+    ///
+    ///     serializer.serialize_u32(*age)
+    /// }
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     #[serde(serialize_with = "checked_age")]
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/update_failed");
+    ///     let db = Database::new("data/update_failed", None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user)
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     let original = std::fs::read("data/update_failed/users.csv").unwrap();
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 200,
+    ///     };
+    ///
+    ///     assert!(db.update("users", user, |u: &User| u.id == 1).await.is_err());
+    ///     assert_eq!(std::fs::read("data/update_failed/users.csv").unwrap(), original);
+    ///     assert!(!std::path::Path::new("data/update_failed/users.csv.tmp").exists());
+    /// }
+    /// ```
+    pub async fn update<T, P>(
+        &self,
+        collection: &str,
+        document: T,
+        predicate: P,
+    ) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + Send + 'static,
+        P: FnMut(&T) -> bool,
+    {
+        let _lock = self.lock(collection).await;
+        let documents: Vec<T> = self.find(collection, |_| true).await?;
+
+        let documents = replace_first(documents, document, predicate);
+
+        Ok(self.write(collection, documents).await??)
+    }
+
+    /// Update a document by filtering with a predicate on a collection, after setting its update
+    /// timestamp to the current time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Database, Timestamped};
+    /// use serde::{Deserialize, Serialize};
+    /// use std::time::{SystemTime, UNIX_EPOCH};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    ///     created_at: u128,
+    ///     updated_at: u128,
+    /// }
+    ///
+    /// impl Timestamped for User {
+    ///     fn set_created_at(&mut self, time: SystemTime) {
+    ///         self.created_at = time.duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    ///     }
+    ///
+    ///     fn set_updated_at(&mut self, time: SystemTime) {
+    ///         self.updated_at = time.duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/update_timestamped");
+    ///     let db = Database::new("data/update_timestamped", None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///         created_at: 0,
+    ///         updated_at: 0,
+    ///     };
+    ///
+    ///     db.insert_timestamped("users", user)
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     let mut user = db
+    ///         .find("users", |u: &User| u.id == 1)
+    ///         .await
+    ///         .expect("Problem searching user.")
+    ///         .remove(0);
+    ///     let created_at = user.created_at;
+    ///
+    ///     user.age = 21;
+    ///
+    ///     db.update_timestamped("users", user, |u: &User| u.id == 1)
+    ///         .await
+    ///         .expect("Problem updating user.");
+    ///
+    ///     let users = db
+    ///         .find("users", |u: &User| u.id == 1)
+    ///         .await
+    ///         .expect("Problem searching user.");
+    ///
+    ///     assert_eq!(users[0].created_at, created_at);
+    ///     assert!(users[0].updated_at > created_at);
+    /// }
+    /// ```
+    pub async fn update_timestamped<T, P>(
+        &self,
+        collection: &str,
+        mut document: T,
+        predicate: P,
+    ) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + Timestamped + Send + 'static,
+        P: FnMut(&T) -> bool,
+    {
+        document.set_updated_at(SystemTime::now());
+
+        self.update(collection, document, predicate).await
+    }
+
+    /// Update a document by filtering with a predicate on a collection, only if the stored document
+    /// has the same version as the new one, returning the bumped version.
+    ///
+    /// The first matching document is replaced in place with the new document, whose version is
+    /// incremented. If the stored version differs, because the document was updated since it was
+    /// read, or if no document matches, a [`DbError::Conflict`] is returned instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Database, DbError, Versioned};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    ///     version: u64,
+    /// }
+    ///
+    /// impl Versioned for User {
+    ///     fn version(&self) -> u64 {
+    ///         self.version
+    ///     }
+    ///
+    ///     fn set_version(&mut self, version: u64) {
+    ///         self.version = version;
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/update_versioned");
+    ///     let db = Database::new("data/update_versioned", None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///         version: 0,
+    ///     };
+    ///
+    ///     db.insert("users", user.clone())
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     let mut fresh = user.clone();
+    ///     fresh.age = 21;
+    ///
+    ///     let version = db
+    ///         .update_versioned("users", fresh, |u: &User| u.id == 1)
+    ///         .await
+    ///         .expect("Problem updating user.");
+    ///
+    ///     assert_eq!(version, 1);
+    ///
+    ///     let mut stale = user;
+    ///     stale.age = 22;
+    ///
+    ///     match db.update_versioned("users", stale, |u: &User| u.id == 1).await {
+    ///         Err(DbError::Conflict { .. }) => {}
+    ///         result => panic!("Expected a conflict, got {:?}.", result),
+    ///     }
+    ///
+    ///     let users = db
+    ///         .find("users", |u: &User| u.id == 1)
+    ///         .await
+    ///         .expect("Problem searching user.");
+    ///
+    ///     assert_eq!((users[0].age, users[0].version), (21, 1));
+    /// }
+    /// ```
+    pub async fn update_versioned<T, P>(
+        &self,
+        collection: &str,
+        mut document: T,
+        mut predicate: P,
+    ) -> Result<u64, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Versioned + Send + 'static,
+        P: FnMut(&T) -> bool,
+    {
+        let _lock = self.lock(collection).await;
+        let mut documents: Vec<T> = self.find(collection, |_| true).await?;
+
+        let stored = match documents.iter_mut().find(|d| predicate(d)) {
+            Some(stored) if stored.version() == document.version() => stored,
+            _ => {
+                return Err(DbError::Conflict {
+                    collection: collection.to_string(),
+                })
+            }
+        };
+
+        let version = document.version() + 1;
+        document.set_version(version);
+        *stored = document;
+
+        self.write(collection, documents).await??;
+
+        Ok(version)
+    }
+
+    /// Update all documents matching a predicate on a collection, by applying an in-place mutation
+    /// to each of them. Documents that don't match are left untouched and the order is preserved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/update_many");
+    ///     let db = Database::new("data/update_many", None);
+    ///
+    ///     let users = [(1, 20), (2, 20), (3, 30)]
+    ///         .into_iter()
+    ///         .map(|(id, age)| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     db.update_many("users", |u: &User| u.age == 20, |u: &mut User| u.age += 1)
+    ///         .await
+    ///         .expect("Problem updating users.");
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(
+    ///         users.iter().map(|u| (u.id, u.age)).collect::<Vec<_>>(),
+    ///         vec![(1, 21), (2, 21), (3, 30)]
+    ///     );
+    /// }
+    /// ```
+    pub async fn update_many<T, P, F>(
+        &self,
+        collection: &str,
+        mut predicate: P,
+        update_fn: F,
+    ) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + Send + 'static,
+        P: FnMut(&T) -> bool,
+        F: FnMut(&mut T),
+    {
+        let _lock = self.lock(collection).await;
+        let mut documents: Vec<T> = self.find(collection, |_| true).await?;
+
+        documents
+            .iter_mut()
+            .filter(|d| predicate(d))
+            .for_each(update_fn);
+
+        Ok(self.write(collection, documents).await??)
+    }
+
+    /// Transform every document of a collection with a function, rewriting them in the same order
+    /// and returning how many documents were transformed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/map_collection");
+    ///     let db = Database::new("data/map_collection", None);
+    ///
+    ///     let users = (1..=2)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let transformed = db
+    ///         .map_collection("users", |mut u: User| {
+    ///             u.first_name = u.first_name.to_uppercase();
+    ///             u
+    ///         })
+    ///         .await
+    ///         .expect("Problem transforming users.");
+    ///
+    ///     assert_eq!(transformed, 2);
+    ///     assert_eq!(
+    ///         std::fs::read_to_string("data/map_collection/users.csv").unwrap(),
+    ///         "id,first_name,last_name,age\n1,FIRST,Last,20\n2,FIRST,Last,20\n"
+    ///     );
+    /// }
+    /// ```
+    pub async fn map_collection<T, F>(&self, collection: &str, f: F) -> Result<usize, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        F: FnMut(T) -> T,
+    {
+        let _lock = self.lock(collection).await;
+        let documents: Vec<T> = self.find(collection, |_| true).await?;
+        let transformed = documents.len();
+
+        let documents = documents.into_iter().map(f).collect();
+
+        self.write(collection, documents).await??;
+
+        Ok(transformed)
+    }
+
+    /// Replace the documents of a collection whose key is in a map with the document it maps to,
+    /// reading and writing the collection only once and returning how many documents were
+    /// replaced.
+    ///
+    /// Replaced documents keep their position in the collection, while documents whose key isn't
+    /// in the map stay unchanged. Each entry replaces only the first document with its key, and
+    /// entries that match no document are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    /// use std::collections::HashMap;
+    ///
+    /// #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/update_by_map");
+    ///     let db = Database::new("data/update_by_map", None);
+    ///
+    ///     let users: Vec<User> = (1..=10)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users.clone())
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let updates: HashMap<usize, User> = [2, 5, 9]
+    ///         .into_iter()
+    ///         .map(|id| {
+    ///             let user = User {
+    ///                 age: 30,
+    ///                 ..users[id - 1].clone()
+    ///             };
+    ///
+    ///             (id, user)
+    ///         })
+    ///         .collect();
+    ///
+    ///     let replaced = db
+    ///         .update_by_map("users", updates, |u: &User| u.id)
+    ///         .await
+    ///         .expect("Problem updating users.");
+    ///
+    ///     assert_eq!(replaced, 3);
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 10);
+    ///
+    ///     for (i, user) in users.iter().enumerate() {
+    ///         assert_eq!(user.id, i + 1);
+    ///         assert_eq!(user.age, if [2, 5, 9].contains(&user.id) { 30 } else { 20 });
+    ///     }
+    /// }
+    /// ```
+    pub async fn update_by_map<T, K, F>(
+        &self,
+        collection: &str,
+        mut updates: HashMap<K, T>,
+        key: F,
+    ) -> Result<usize, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        K: Eq + Hash,
+        F: Fn(&T) -> K,
+    {
+        let _lock = self.lock(collection).await;
+        let documents: Vec<T> = self.find(collection, |_| true).await?;
+        let mut replaced = 0;
+
+        let documents = documents
+            .into_iter()
+            .map(|d| match updates.remove(&key(&d)) {
+                Some(update) => {
+                    replaced += 1;
+                    update
+                }
+                None => d,
+            })
+            .collect();
+
+        self.write(collection, documents).await??;
+
+        Ok(replaced)
+    }
+
+    /// Soft delete documents by filtering with a predicate on a collection, marking them as
+    /// deleted while keeping them in the collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Database, SoftDeletable};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    ///     deleted: bool,
+    /// }
+    ///
+    /// impl SoftDeletable for User {
+    ///     fn is_deleted(&self) -> bool {
+    ///         self.deleted
+    ///     }
+    ///
+    ///     fn set_deleted(&mut self, deleted: bool) {
+    ///         self.deleted = deleted;
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/soft_delete");
+    ///     let db = Database::new("data/soft_delete", None);
+    ///
+    ///     let users = (1..=3)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///             deleted: false,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     db.soft_delete("users", |u: &User| u.id == 2)
+    ///         .await
+    ///         .expect("Problem deleting user.");
+    ///
+    ///     let active = db
+    ///         .find_active("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(active.iter().map(|u| u.id).collect::<Vec<_>>(), vec![1, 3]);
+    ///     assert_eq!(db.count("users").await.expect("Problem counting users."), 3);
+    /// }
+    /// ```
+    pub async fn soft_delete<T, P>(&self, collection: &str, predicate: P) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + SoftDeletable + Send + 'static,
+        P: FnMut(&T) -> bool,
+    {
+        self.update_many(collection, predicate, |d: &mut T| d.set_deleted(true))
+            .await
+    }
+
+    /// Find documents that aren't soft deleted by filtering with a predicate on a collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Database, SoftDeletable};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    ///     deleted: bool,
+    /// }
+    ///
+    /// impl SoftDeletable for User {
+    ///     fn is_deleted(&self) -> bool {
+    ///         self.deleted
+    ///     }
+    ///
+    ///     fn set_deleted(&mut self, deleted: bool) {
+    ///         self.deleted = deleted;
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_active");
+    ///     let db = Database::new("data/find_active", None);
+    ///
+    ///     let users = [(1, false), (2, true)]
+    ///         .into_iter()
+    ///         .map(|(id, deleted)| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///             deleted,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let adults = db
+    ///         .find_active("users", |u: &User| u.age >= 18)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(adults.iter().map(|u| u.id).collect::<Vec<_>>(), vec![1]);
+    /// }
+    /// ```
+    pub async fn find_active<T, P>(
+        &self,
+        collection: &str,
+        mut predicate: P,
+    ) -> Result<Vec<T>, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + SoftDeletable + Send + 'static,
+        P: FnMut(&T) -> bool,
+    {
+        self.find(collection, |d: &T| !d.is_deleted() && predicate(d))
+            .await
+    }
+
+    /// Purge a collection by removing all of its soft deleted documents, returning how many
+    /// documents were removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Database, SoftDeletable};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    ///     deleted: bool,
+    /// }
+    ///
+    /// impl SoftDeletable for User {
+    ///     fn is_deleted(&self) -> bool {
+    ///         self.deleted
+    ///     }
+    ///
+    ///     fn set_deleted(&mut self, deleted: bool) {
+    ///         self.deleted = deleted;
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/purge");
+    ///     let db = Database::new("data/purge", None);
+    ///
+    ///     let users = [(1, false), (2, true)]
+    ///         .into_iter()
+    ///         .map(|(id, deleted)| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///             deleted,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let purged = db
+    ///         .purge::<User>("users")
+    ///         .await
+    ///         .expect("Problem purging users.");
+    ///
+    ///     assert_eq!(purged, 1);
+    /// }
+    /// ```
+    pub async fn purge<T>(&self, collection: &str) -> Result<usize, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + SoftDeletable + Send + 'static,
+    {
+        self.delete(collection, |d: &T| d.is_deleted()).await
+    }
+
+    /// Modify documents in place by filtering with a predicate on a collection, applying a
+    /// mutation to each matching document while preserving the order of the collection.
+    ///
+    /// This is the same as [`Database::update_many`], for when a single field needs changing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/modify");
+    ///     let db = Database::new("data/modify", None);
+    ///
+    ///     let users = (1..=3)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     db.modify("users", |u: &User| u.id == 2, |u: &mut User| u.age += 1)
+    ///         .await
+    ///         .expect("Problem modifying user.");
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(
+    ///         users.iter().map(|u| (u.id, u.age)).collect::<Vec<_>>(),
+    ///         vec![(1, 20), (2, 21), (3, 20)]
+    ///     );
+    /// }
+    /// ```
+    pub async fn modify<T, P, F>(
+        &self,
+        collection: &str,
+        predicate: P,
+        mutate: F,
+    ) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + Send + 'static,
+        P: FnMut(&T) -> bool,
+        F: FnMut(&mut T),
+    {
+        self.update_many(collection, predicate, mutate).await
+    }
+
+    /// Update a document by filtering with a predicate on a collection, or insert it when no
+    /// document matches.
+    ///
+    /// Unlike [`Database::update`], all matching documents are removed and the new document is
+    /// inserted at the end of the collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/upsert");
+    ///     let db = Database::new("data/upsert", None);
+    ///
+    ///     for age in [20, 21] {
+    ///         let user = User {
+    ///             id: 1,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age,
+    ///         };
+    ///
+    ///         db.upsert("users", user, |u: &User| u.id == 1)
+    ///             .await
+    ///             .expect("Problem upserting user.");
+    ///     }
+    ///
+    ///     let users = db
+    ///         .find("users", |u: &User| u.id == 1)
+    ///         .await
+    ///         .expect("Problem searching user.");
+    ///
+    ///     assert_eq!(users.len(), 1);
+    ///     assert_eq!(users[0].age, 21);
+    /// }
+    /// ```
+    pub async fn upsert<T, P>(
+        &self,
+        collection: &str,
+        document: T,
+        mut predicate: P,
+    ) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + Send + 'static,
+        P: FnMut(&T) -> bool,
+    {
+        let _lock = self.lock(collection).await;
+        let mut documents: Vec<T> = self.find(collection, |_| true).await?;
+
+        documents.retain(|d| !predicate(d));
+        documents.push(document);
+
+        Ok(self.write(collection, documents).await??)
+    }
+
+    /// The path of the file that stores a collection, which needn't exist yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Compression, Database, DatabaseBuilder};
+    /// use std::path::PathBuf;
+    ///
+    /// let db = Database::new("data/collection_path", Some("txt"));
+    ///
+    /// assert_eq!(
+    ///     db.collection_path("users"),
+    ///     PathBuf::from("data/collection_path/users.txt")
+    /// );
+    ///
+    /// let db = DatabaseBuilder::new("data/collection_path")
+    ///     .compression(Compression::Gzip)
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     db.collection_path("users"),
+    ///     PathBuf::from("data/collection_path/users.csv.gz")
+    /// );
+    /// ```
+    pub fn collection_path(&self, collection: &str) -> PathBuf {
+        self.config.collection_path(collection)
+    }
+
+    /// The size in bytes of the file that stores a collection, or 0 if the collection doesn't
+    /// exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/size_on_disk");
+    ///     let db = Database::new("data/size_on_disk", None);
+    ///
+    ///     assert_eq!(db.size_on_disk("users").await.expect("Problem sizing users."), 0);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user).await.expect("Problem inserting user.");
+    ///
+    ///     let size = db.size_on_disk("users").await.expect("Problem sizing users.");
+    ///     let len = std::fs::read("data/size_on_disk/users.csv").unwrap().len() as u64;
+    ///
+    ///     assert_eq!(size, len);
+    /// }
+    /// ```
+    pub async fn size_on_disk(&self, collection: &str) -> Result<u64, DbError> {
+        let path = self.config.collection_path(collection);
+        let result: Result<Result<u64, io::Error>, JoinError> =
+            task::spawn_blocking(move || match std::fs::metadata(path) {
+                Ok(metadata) => Ok(metadata.len()),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+                Err(e) => Err(e),
+            })
+            .await;
+
+        Ok(result??)
+    }
+
+    /// The time when the file that stores a collection was last modified, or none if the
+    /// collection doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    /// use std::time::Duration;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/last_modified");
+    ///     let db = Database::new("data/last_modified", None);
+    ///
+    ///     let modified = db.last_modified("users").await.expect("Problem reading users.");
+    ///
+    ///     assert_eq!(modified, None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user).await.expect("Problem inserting user.");
+    ///
+    ///     let first = db.last_modified("users").await.expect("Problem reading users.");
+    ///
+    ///     tokio::time::sleep(Duration::from_millis(50)).await;
+    ///
+    ///     let user = User {
+    ///         id: 2,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user).await.expect("Problem inserting user.");
+    ///
+    ///     let second = db.last_modified("users").await.expect("Problem reading users.");
+    ///
+    ///     assert!(second > first);
+    /// }
+    /// ```
+    pub async fn last_modified(&self, collection: &str) -> Result<Option<SystemTime>, DbError> {
+        let path = self.config.collection_path(collection);
+        let result: Result<Result<Option<SystemTime>, io::Error>, JoinError> =
+            task::spawn_blocking(move || match std::fs::metadata(path) {
+                Ok(metadata) => Ok(Some(metadata.modified()?)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e),
+            })
+            .await;
+
+        Ok(result??)
+    }
+
+    /// Summarize a collection, reading its file only once.
+    ///
+    /// A collection that doesn't exist is summarized with the default [`CollectionMeta`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/metadata");
+    ///     let db = Database::new("data/metadata", None);
+    ///
+    ///     let meta = db.metadata("users").await.expect("Problem reading metadata.");
+    ///
+    ///     assert!(!meta.exists);
+    ///
+    ///     let users = (1..=3)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let meta = db.metadata("users").await.expect("Problem reading metadata.");
+    ///     let file = std::fs::metadata("data/metadata/users.csv").unwrap();
+    ///
+    ///     assert!(meta.exists);
+    ///     assert_eq!(meta.row_count, 3);
+    ///     assert_eq!(meta.size_bytes, file.len());
+    ///     assert_eq!(meta.last_modified, Some(file.modified().unwrap()));
+    ///     assert_eq!(
+    ///         meta.header,
+    ///         Some(vec![
+    ///             String::from("id"),
+    ///             String::from("first_name"),
+    ///             String::from("last_name"),
+    ///             String::from("age"),
+    ///         ])
+    ///     );
+    /// }
+    /// ```
+    pub async fn metadata(&self, collection: &str) -> Result<CollectionMeta, DbError> {
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let result: Result<Result<CollectionMeta, csv::Error>, JoinError> =
+            task::spawn_blocking(move || {
+                let metadata = match std::fs::metadata(config.collection_path(&collection)) {
+                    Ok(metadata) => metadata,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                        return Ok(CollectionMeta::default())
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+
+                let mut rdr = config.reader(&collection)?;
+                let header = if config.has_headers {
+                    Some(rdr.headers()?.iter().map(String::from).collect::<Vec<_>>())
+                        .filter(|header| !header.is_empty())
+                } else {
+                    None
+                };
+
+                let mut record = ByteRecord::new();
+                let mut row_count = 0;
+
+                while rdr.read_byte_record(&mut record)? {
+                    row_count += 1;
+                }
+
+                Ok(CollectionMeta {
+                    exists: true,
+                    row_count,
+                    size_bytes: metadata.len(),
+                    last_modified: Some(metadata.modified()?),
+                    header,
+                })
+            })
+            .await;
+
+        Ok(result??)
+    }
+
+    /// Read the names of the columns in the header of a collection, without reading its rows.
+    ///
+    /// None is returned if the collection doesn't exist, is empty, or if the database is
+    /// configured without headers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/headers");
+    ///     let db = Database::new("data/headers", None);
+    ///
+    ///     assert_eq!(db.headers("users").await.expect("Problem reading headers."), None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user).await.expect("Problem inserting user.");
+    ///
+    ///     assert_eq!(
+    ///         db.headers("users").await.expect("Problem reading headers."),
+    ///         Some(vec![
+    ///             String::from("id"),
+    ///             String::from("first_name"),
+    ///             String::from("last_name"),
+    ///             String::from("age"),
+    ///         ])
+    ///     );
+    /// }
+    /// ```
+    pub async fn headers(&self, collection: &str) -> Result<Option<Vec<String>>, DbError> {
+        if !self.config.has_headers {
+            return Ok(None);
+        }
+
+        self.read(collection, None, |rdr| {
+            let headers: Vec<String> = rdr.headers()?.iter().map(String::from).collect();
+
+            Ok(Some(headers).filter(|headers| !headers.is_empty()))
+        })
+        .await
+    }
+
+    /// Drop a collection by removing its file. Dropping a collection that doesn't exist succeeds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/drop_collection");
+    ///     let db = Database::new("data/drop_collection", None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user)
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     db.drop_collection("users")
+    ///         .await
+    ///         .expect("Problem dropping users.");
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert!(users.is_empty());
+    /// }
+    /// ```
+    pub async fn drop_collection(&self, collection: &str) -> Result<(), DbError> {
+        let _lock = self.lock(collection).await;
+        self.invalidate(collection);
+
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let result: Result<Result<(), std::io::Error>, JoinError> =
+            task::spawn_blocking(move || {
+                let path = config.collection_path(&collection);
+
+                remove_if_exists(&path)?;
+                remove_if_exists(&checksum_path(&path))?;
+                remove_if_exists(&index_path(&path))
+            })
+            .await;
+
+        Ok(result??)
+    }
+
+    /// List the names of all collections under the path of the database, sorted alphabetically.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/list_collections");
+    ///     let db = Database::new("data/list_collections", None);
+    ///
+    ///     for collection in ["users", "admins"] {
+    ///         let user = User {
+    ///             id: 1,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         };
+    ///
+    ///         db.insert(collection, user)
+    ///             .await
+    ///             .expect("Problem inserting user.");
+    ///     }
+    ///
+    ///     let collections = db
+    ///         .list_collections()
+    ///         .await
+    ///         .expect("Problem listing collections.");
+    ///
+    ///     assert_eq!(collections, vec!["admins", "users"]);
+    /// }
+    /// ```
+    pub async fn list_collections(&self) -> Result<Vec<String>, DbError> {
+        let config = self.config.clone();
+        let result: Result<Result<Vec<String>, std::io::Error>, JoinError> =
+            task::spawn_blocking(move || {
+                let entries = match std::fs::read_dir(config.path.as_ref()) {
+                    Ok(entries) => entries,
+                    Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                        return Ok(Vec::new())
+                    }
+                    Err(error) => return Err(error),
+                };
+
+                let mut collections = Vec::new();
+
+                for entry in entries {
+                    if let Some(name) = config.collection_name(&entry?.path()) {
+                        collections.push(name);
+                    }
+                }
+
+                collections.sort();
+
+                Ok(collections)
+            })
+            .await;
+
+        Ok(result??)
+    }
+
+    /// Rename a collection by moving its file, without rewriting its documents.
+    ///
+    /// Returns [`DbError::NotFound`] if the source collection doesn't exist and
+    /// [`DbError::AlreadyExists`] if the destination collection already exists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Database, DbError};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/rename_collection");
+    ///     let db = Database::new("data/rename_collection", None);
+    ///
+    ///     for collection in ["users", "admins"] {
+    ///         let user = User {
+    ///             id: 1,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         };
+    ///
+    ///         db.insert(collection, user)
+    ///             .await
+    ///             .expect("Problem inserting user.");
+    ///     }
+    ///
+    ///     db.rename_collection("users", "users_v1")
+    ///         .await
+    ///         .expect("Problem renaming users.");
+    ///
+    ///     assert!(matches!(
+    ///         db.rename_collection("admins", "users_v1").await,
+    ///         Err(DbError::AlreadyExists { .. })
+    ///     ));
+    ///     assert!(matches!(
+    ///         db.rename_collection("users", "users_v2").await,
+    ///         Err(DbError::NotFound { .. })
+    ///     ));
+    /// }
+    /// ```
+    pub async fn rename_collection(&self, from: &str, to: &str) -> Result<(), DbError> {
+        let (first, second) = if from <= to { (from, to) } else { (to, from) };
+        let _first_lock = self.lock(first).await;
+        let _second_lock = if first != second {
+            Some(self.lock(second).await)
+        } else {
+            None
+        };
+
+        self.invalidate(from);
+        self.invalidate(to);
+
+        let from = from.to_string();
+        let to = to.to_string();
+        let config = self.config.clone();
+        let result: Result<Result<(), DbError>, JoinError> = task::spawn_blocking(move || {
+            let from_path = config.collection_path(&from);
+            let to_path = config.collection_path(&to);
+
+            if !from_path.exists() {
+                return Err(DbError::NotFound { collection: from });
+            }
+
+            if to_path.exists() {
+                return Err(DbError::AlreadyExists { collection: to });
+            }
+
+            std::fs::rename(&from_path, &to_path)?;
+
+            // The checksum moves along with the file instead of being recomputed, so that a file
+            // corrupted before the rename still fails its verification afterwards.
+            match std::fs::rename(checksum_path(&from_path), checksum_path(&to_path)) {
+                Ok(()) => {}
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                    remove_if_exists(&checksum_path(&to_path))?
+                }
+                Err(error) => return Err(error.into()),
+            }
+
+            remove_if_exists(&index_path(&from_path))?;
+            remove_if_exists(&index_path(&to_path))?;
+
+            Ok(())
+        })
+        .await;
+
+        result?
+    }
+
+    /// Backup a collection by copying its file to a destination path, creating any missing
+    /// parent folders of the destination.
+    ///
+    /// Returns [`DbError::NotFound`] if the collection doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/backup");
+    ///     let db = Database::new("data/backup", None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user)
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     db.backup("users", "data/backup/snapshots/users.csv")
+    ///         .await
+    ///         .expect("Problem backing up users.");
+    ///
+    ///     assert_eq!(
+    ///         std::fs::read("data/backup/snapshots/users.csv").unwrap(),
+    ///         std::fs::read("data/backup/users.csv").unwrap()
+    ///     );
+    /// }
+    /// ```
+    pub async fn backup(&self, collection: &str, dest: impl AsRef<Path>) -> Result<(), DbError> {
+        let _lock = self.lock(collection).await;
+
+        let collection = collection.to_string();
+        let dest = dest.as_ref().to_path_buf();
+        let config = self.config.clone();
+        let result: Result<Result<(), DbError>, JoinError> = task::spawn_blocking(move || {
+            let path = config.collection_path(&collection);
+
+            if !path.exists() {
+                return Err(DbError::NotFound { collection });
+            }
+
+            if let Some(parent_path) = dest.parent() {
+                std::fs::create_dir_all(parent_path)?
+            }
+
+            std::fs::copy(path, dest)?;
+
+            Ok(())
+        })
+        .await;
+
+        result?
+    }
+
+    /// Backup all collections by copying their files into a destination folder, creating it if
+    /// needed, and returning how many collections were exported.
+    ///
+    /// Each file keeps its name, so the destination folder can be used as the path of another
+    /// Database with the same configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/export_all");
+    ///     let db = Database::new("data/export_all/db", None);
+    ///
+    ///     for collection in ["admins", "guests", "users"] {
+    ///         let user = User {
+    ///             id: 1,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         };
+    ///
+    ///         db.insert(collection, user)
+    ///             .await
+    ///             .expect("Problem inserting user.");
+    ///     }
+    ///
+    ///     let exported = db
+    ///         .export_all("data/export_all/backup")
+    ///         .await
+    ///         .expect("Problem exporting collections.");
+    ///
+    ///     assert_eq!(exported, 3);
+    ///
+    ///     for collection in ["admins", "guests", "users"] {
+    ///         let path = format!("data/export_all/backup/{}.csv", collection);
+    ///
+    ///         assert!(std::path::Path::new(&path).exists());
+    ///     }
+    /// }
+    /// ```
+    pub async fn export_all(&self, dest: impl AsRef<Path>) -> Result<usize, DbError> {
+        let dest = dest.as_ref();
+        let collections = self.list_collections().await?;
+
+        for collection in &collections {
+            let path = self.config.collection_path(collection);
+            // Collection paths always end with the name of the collection and its extension.
+            let file_name = path
+                .file_name()
+                .expect("Collection path without file name.");
+
+            self.backup(collection, dest.join(file_name)).await?;
+        }
+
+        Ok(collections.len())
+    }
+
+    /// Restore collections from all files in a source folder that have the extension of
+    /// collections, each named after its file, returning how many collections were imported.
+    ///
+    /// Files with other extensions and subfolders are skipped. An imported file replaces the
+    /// collection of the same name, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/import_all");
+    /// #   std::fs::create_dir_all("data/import_all/src").unwrap();
+    /// #   for (file, id) in [("admins.csv", 1), ("users.csv", 2), ("notes.txt", 3)] {
+    /// #       std::fs::write(
+    /// #           format!("data/import_all/src/{}", file),
+    /// #           format!("id,first_name,last_name,age\n{},First,Last,20\n", id),
+    /// #       )
+    /// #       .unwrap();
+    /// #   }
+    ///     let db = Database::new("data/import_all/db", None);
+    ///
+    ///     let imported = db
+    ///         .import_all("data/import_all/src")
+    ///         .await
+    ///         .expect("Problem importing collections.");
+    ///
+    ///     assert_eq!(imported, 2);
+    ///
+    ///     for (collection, id) in [("admins", 1), ("users", 2)] {
+    ///         let users = db
+    ///             .find(collection, |u: &User| u.id == id)
+    ///             .await
+    ///             .expect("Problem searching users.");
+    ///
+    ///         assert_eq!(users.len(), 1);
+    ///     }
+    ///
+    ///     assert_eq!(
+    ///         db.list_collections().await.expect("Problem listing collections."),
+    ///         vec!["admins", "users"]
+    ///     );
+    /// }
+    /// ```
+    pub async fn import_all(&self, src: impl AsRef<Path>) -> Result<usize, DbError> {
+        let src = src.as_ref().to_path_buf();
+        let config = self.config.clone();
+        let result: Result<Result<Vec<(String, PathBuf)>, std::io::Error>, JoinError> =
+            task::spawn_blocking(move || {
+                let mut files = Vec::new();
+
+                for entry in std::fs::read_dir(src)? {
+                    let path = entry?.path();
+
+                    if let Some(name) = config.collection_name(&path) {
+                        files.push((name, path));
+                    }
+                }
+
+                Ok(files)
+            })
+            .await;
+
+        let files = result??;
+
+        for (collection, path) in &files {
+            self.restore(collection, path).await?;
+        }
+
+        Ok(files.len())
+    }
+
+    /// Restore a collection by copying a backup file over its file, atomically replacing all of
+    /// its contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    /// struct User {
+    ///     id: usize,
+    ///     first_name: String,
+    ///     last_name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/restore");
+    ///     let db = Database::new("data/restore", None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
+    ///
+    ///     db.insert("users", user)
+    ///         .await
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     db.backup("users", "data/restore/snapshots/users.csv")
+    ///         .await
+    ///         .expect("Problem backing up users.");
+    ///
+    ///     db.delete("users", |u: &User| u.id == 1)
+    ///         .await
+    ///         .expect("Problem deleting user.");
+    ///
+    ///     db.restore("users", "data/restore/snapshots/users.csv")
+    ///         .await
+    ///         .expect("Problem restoring users.");
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.len(), 1);
+    ///     assert_eq!(users[0].id, 1);
+    /// }
+    /// ```
+    pub async fn restore(&self, collection: &str, src: impl AsRef<Path>) -> Result<(), DbError> {
+        let _lock = self.lock(collection).await;
+        self.invalidate(collection);
+
+        let collection = collection.to_string();
+        let src = src.as_ref().to_path_buf();
+        let config = self.config.clone();
+        let result: Result<Result<(), std::io::Error>, JoinError> =
+            task::spawn_blocking(move || {
+                File::open(&src)?;
+
+                let path = config.collection_path(&collection);
+
+                if let Some(parent_path) = path.parent() {
+                    std::fs::create_dir_all(parent_path)?
+                }
+
+                let tmp_path = tmp_path(&path);
+
+                match std::fs::copy(&src, &tmp_path) {
+                    Ok(_) => {
+                        std::fs::rename(&tmp_path, &path)?;
+                        remove_if_exists(&index_path(&path))?;
+                        config.store_checksum(&path)
+                    }
+                    Err(error) => {
+                        let _ = std::fs::remove_file(&tmp_path);
+
+                        Err(error)
+                    }
+                }
+            })
+            .await;
+
+        Ok(result??)
+    }
+
+    /// Export all documents of a collection to a destination path as a JSON array, creating any
+    /// missing parent folders of the destination.
+    ///
+    /// Requires the `json` feature.
     ///
     /// # Examples
     ///
@@ -125,19 +7882,70 @@ where
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let db = Database::new("data", None);
+    /// #   let _ = std::fs::remove_dir_all("data/export_json");
+    ///     let db = Database::new("data/export_json", None);
+    ///
+    ///     let users = (1..=2)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.insert_many("users", users)
+    ///         .await
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     db.export_json::<User>("users", "data/export_json/users.json")
+    ///         .await
+    ///         .expect("Problem exporting users.");
+    ///
+    ///     let json = std::fs::read_to_string("data/export_json/users.json").unwrap();
+    ///     let exported: Vec<User> = serde_json::from_str(&json).unwrap();
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(exported, users);
     /// }
     /// ```
-    pub fn new(path: PA, extension: Option<&str>) -> Self {
-        Self {
-            config: Arc::new(Config {
-                path,
-                extension: String::from(extension.unwrap_or("csv")),
-            }),
-        }
+    #[cfg(feature = "json")]
+    pub async fn export_json<T>(
+        &self,
+        collection: &str,
+        dest: impl AsRef<Path>,
+    ) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    {
+        let documents: Vec<T> = self.find(collection, |_| true).await?;
+
+        let dest = dest.as_ref().to_path_buf();
+        let result: Result<Result<(), DbError>, JoinError> = task::spawn_blocking(move || {
+            if let Some(parent_path) = dest.parent() {
+                std::fs::create_dir_all(parent_path)?
+            }
+
+            let mut wrt = io::BufWriter::new(File::create(dest)?);
+
+            serde_json::to_writer(&mut wrt, &documents)?;
+            wrt.flush()?;
+
+            Ok(())
+        })
+        .await;
+
+        result?
     }
 
-    /// Find documents by filtering with a predicate on a collection.
+    /// Import documents into a collection from a JSON array at a source path, replacing all of
+    /// its contents and returning how many documents were imported.
+    ///
+    /// Requires the `json` feature.
     ///
     /// # Examples
     ///
@@ -155,47 +7963,63 @@ where
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let db = Database::new("data", None);
+    /// #   let _ = std::fs::remove_dir_all("data/import_json");
+    /// #   std::fs::create_dir_all("data/import_json").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/import_json/users.json",
+    /// #       r#"[{"id":1,"first_name":"First","last_name":"Last","age":20},
+    /// #           {"id":2,"first_name":"First","last_name":"Last","age":30}]"#,
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = Database::new("data/import_json", None);
     ///
-    ///     let user = User {
-    ///         id: 1,
-    ///         first_name: String::from("First"),
-    ///         last_name: String::from("Last"),
-    ///         age: 20,
-    ///     };
+    ///     let imported = db
+    ///         .import_json::<User>("users", "data/import_json/users.json")
+    ///         .await
+    ///         .expect("Problem importing users.");
     ///
-    ///     let adults = db
-    ///         .find("users", |u: &User| u.age >= 18)
+    ///     assert_eq!(imported, 2);
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
     ///         .await
-    ///         .expect("Problem searching user.");
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.iter().map(|u| u.age).collect::<Vec<_>>(), vec![20, 30]);
     /// }
     /// ```
-    pub async fn find<T, P>(&self, collection: &str, predicate: P) -> Result<Vec<T>, Box<dyn Error>>
+    #[cfg(feature = "json")]
+    pub async fn import_json<T>(
+        &self,
+        collection: &str,
+        src: impl AsRef<Path>,
+    ) -> Result<usize, DbError>
     where
         T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
-        P: FnMut(&T) -> bool,
     {
+        let _lock = self.lock(collection).await;
+        self.invalidate(collection);
+
         let collection = collection.to_string();
+        let src = src.as_ref().to_path_buf();
         let config = self.config.clone();
-        let results: Result<Result<Vec<T>, _>, _> = task::spawn_blocking(move || {
-            let mut rdr = match Reader::from_path(
-                config
-                    .path
-                    .as_ref()
-                    .join(format!("{}.{}", collection, config.extension)),
-            ) {
-                Ok(rdr) => rdr,
-                Err(_) => return Ok(Vec::new()),
-            };
+        let result: Result<Result<usize, DbError>, JoinError> = task::spawn_blocking(move || {
+            let rdr = io::BufReader::new(File::open(src)?);
+            let documents: Vec<T> = serde_json::from_reader(rdr)?;
 
-            rdr.deserialize().collect()
+            config.write_all(&collection, &documents, &SkippedRows::default())?;
+
+            Ok(documents.len())
         })
         .await;
 
-        Ok(results??.into_iter().filter(predicate).collect())
+        result?
     }
 
-    /// Insert a new document into a collection.
+    /// Replace all documents of a collection with the given documents, atomically.
+    ///
+    /// Unlike inserts, updates and deletes, this also replaces any rows skipped by
+    /// [`DatabaseBuilder::skip_invalid_rows`].
     ///
     /// # Examples
     ///
@@ -213,32 +8037,64 @@ where
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let db = Database::new("data", None);
+    /// #   let _ = std::fs::remove_dir_all("data/replace_all");
+    ///     let db = Database::new("data/replace_all", None);
     ///
-    ///     let user = User {
-    ///         id: 1,
-    ///         first_name: String::from("First"),
-    ///         last_name: String::from("Last"),
-    ///         age: 20,
-    ///     };
+    ///     let users = (1..=3)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
     ///
-    ///     db.insert("users", user)
+    ///     db.insert_many("users", users)
     ///         .await
-    ///         .expect("Problem inserting user.");
+    ///         .expect("Problem inserting users.");
+    ///
+    ///     let users = (4..=5)
+    ///         .map(|id| User {
+    ///             id,
+    ///             first_name: String::from("First"),
+    ///             last_name: String::from("Last"),
+    ///             age: 20,
+    ///         })
+    ///         .collect();
+    ///
+    ///     db.replace_all("users", users)
+    ///         .await
+    ///         .expect("Problem replacing users.");
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![4, 5]);
     /// }
     /// ```
-    pub async fn insert<T>(&self, collection: &str, document: T) -> Result<(), Box<dyn Error>>
+    pub async fn replace_all<T>(&self, collection: &str, documents: Vec<T>) -> Result<(), DbError>
     where
-        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+        T: Serialize + Send + 'static,
     {
-        let mut documents: Vec<T> = self.find(collection, |_| true).await?;
+        let _lock = self.lock(collection).await;
+        self.invalidate(collection);
 
-        documents.push(document);
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let result: Result<Result<(), csv::Error>, JoinError> = task::spawn_blocking(move || {
+            config.write_all(&collection, &documents, &SkippedRows::default())
+        })
+        .await;
 
-        Ok(self.write(collection, documents).await??)
+        Ok(result??)
     }
 
-    /// Delete a document by filtering with a predicate from a collection.
+    /// Truncate a collection by removing all of its documents, while keeping its header row.
+    ///
+    /// A collection that doesn't exist is left untouched, while a collection without a header row,
+    /// as when [`DatabaseBuilder::has_headers`] is false, is left empty.
     ///
     /// # Examples
     ///
@@ -256,30 +8112,157 @@ where
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let db = Database::new("data", None);
+    /// #   let _ = std::fs::remove_dir_all("data/truncate");
+    ///     let db = Database::new("data/truncate", None);
+    ///
+    ///     let user = User {
+    ///         id: 1,
+    ///         first_name: String::from("First"),
+    ///         last_name: String::from("Last"),
+    ///         age: 20,
+    ///     };
     ///
-    ///     db.delete("users", |u: &&User| u.id == 1)
+    ///     db.insert("users", user)
     ///         .await
-    ///         .expect("Problem deleting user.");
+    ///         .expect("Problem inserting user.");
+    ///
+    ///     db.truncate("users")
+    ///         .await
+    ///         .expect("Problem truncating users.");
+    ///
+    ///     assert_eq!(db.count("users").await.expect("Problem counting users."), 0);
+    ///     assert_eq!(
+    ///         std::fs::read_to_string("data/truncate/users.csv").unwrap(),
+    ///         "id,first_name,last_name,age\n"
+    ///     );
+    /// }
+    /// ```
+    pub async fn truncate(&self, collection: &str) -> Result<(), DbError> {
+        let _lock = self.lock(collection).await;
+        self.invalidate(collection);
+
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let result: Result<Result<(), csv::Error>, JoinError> = task::spawn_blocking(move || {
+            let path = config.collection_path(&collection);
+
+            let mut rdr = match config.reader(&collection) {
+                Ok(rdr) => rdr,
+                Err(e) if is_not_found(&e) => return Ok(()),
+                Err(e) => return Err(e),
+            };
+
+            let headers = if config.has_headers {
+                rdr.headers()?.clone()
+            } else {
+                StringRecord::new()
+            };
+            drop(rdr);
+
+            config.replace(&path, |wrt| {
+                if !headers.is_empty() {
+                    wrt.write_record(&config.file_headers(&headers))?;
+                }
+
+                Ok(())
+            })
+        })
+        .await;
+
+        Ok(result??)
+    }
+
+    /// Find all records of a collection as they are stored, without deserializing them, not
+    /// counting the header.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::{Database, StringRecord};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/find_raw");
+    /// #   std::fs::create_dir_all("data/find_raw").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/find_raw/users.csv",
+    /// #       "id,first_name,last_name,age\n1,First,Last,20\n2,First,Last,21\n",
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = Database::new("data/find_raw", None);
+    ///
+    ///     let records = db.find_raw("users").await.expect("Problem reading users.");
+    ///
+    ///     assert_eq!(records.len(), 2);
+    ///     assert_eq!(records[1], vec!["2", "First", "Last", "21"]);
+    ///
+    ///     let records: Vec<StringRecord> = records
+    ///         .into_iter()
+    ///         .map(|record| {
+    ///             record
+    ///                 .iter()
+    ///                 .enumerate()
+    ///                 .map(|(i, field)| if i == 1 { "Changed" } else { field })
+    ///                 .collect()
+    ///         })
+    ///         .collect();
+    ///     let header = StringRecord::from(vec!["id", "first_name", "last_name", "age"]);
+    ///
+    ///     db.write_raw("users", Some(header), records)
+    ///         .await
+    ///         .expect("Problem writing users.");
+    ///
+    ///     assert_eq!(
+    ///         std::fs::read_to_string("data/find_raw/users.csv").unwrap(),
+    ///         "id,first_name,last_name,age\n1,Changed,Last,20\n2,Changed,Last,21\n"
+    ///     );
     /// }
     /// ```
-    pub async fn delete<T, P>(
+    pub async fn find_raw(&self, collection: &str) -> Result<Vec<StringRecord>, DbError> {
+        self.read(collection, Vec::new(), |rdr| rdr.records().collect())
+            .await
+    }
+
+    /// Replace all records of a collection with raw records, written as they are after an
+    /// optional header, without serializing them.
+    ///
+    /// See [`Database::find_raw`] for an example.
+    pub async fn write_raw(
         &self,
         collection: &str,
-        mut predicate: P,
-    ) -> Result<(), Box<dyn Error>>
-    where
-        T: Serialize + for<'de> Deserialize<'de> + PartialEq + Send + 'static,
-        P: FnMut(&&T) -> bool,
-    {
-        let mut documents: Vec<T> = self.find(collection, |_| true).await?;
+        header: Option<StringRecord>,
+        records: Vec<StringRecord>,
+    ) -> Result<(), DbError> {
+        let _lock = self.lock(collection).await;
+        self.invalidate(collection);
+
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let result: Result<Result<(), csv::Error>, JoinError> = task::spawn_blocking(move || {
+            config.replace(&config.collection_path(&collection), |wrt| {
+                if let Some(header) = &header {
+                    wrt.write_record(header)?;
+                }
 
-        documents.retain(|d| !predicate(&d));
+                for record in &records {
+                    wrt.write_record(record)?;
+                }
 
-        Ok(self.write(collection, documents).await??)
+                Ok(())
+            })
+        })
+        .await;
+
+        Ok(result??)
     }
 
-    /// Update a document by filtering with a predicate on a collection.
+    /// Replace all documents of a collection, writing their fields in the order of the given
+    /// headers instead of the order they are declared in.
+    ///
+    /// Each header must name a field of the documents, so the fields of `T` must be a superset of
+    /// the headers, or a [`DbError::Validation`] is returned. Fields that aren't in the headers
+    /// are left out. Documents are serialized as usual first, so nested fields, renamed fields and
+    /// the float precision all apply, and the headers refer to the resulting column names.
     ///
     /// # Examples
     ///
@@ -297,74 +8280,277 @@ where
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let db = Database::new("data", None);
+    /// #   let _ = std::fs::remove_dir_all("data/write_with_headers");
+    ///     let db = Database::new("data/write_with_headers", None);
     ///
-    ///     let user = User {
+    ///     let users = vec![User {
     ///         id: 1,
     ///         first_name: String::from("First"),
     ///         last_name: String::from("Last"),
-    ///         age: 21,
-    ///     };
+    ///         age: 20,
+    ///     }];
     ///
-    ///     db.update("users", user, |u: &&User| u.id == 1)
+    ///     db.write_with_headers("users", users, &["last_name", "first_name", "age", "id"])
     ///         .await
-    ///         .expect("Problem updating user.");
+    ///         .expect("Problem writing users.");
+    ///
+    ///     assert_eq!(
+    ///         std::fs::read_to_string("data/write_with_headers/users.csv").unwrap(),
+    ///         "last_name,first_name,age,id\nLast,First,20,1\n"
+    ///     );
+    ///
+    ///     let users = db
+    ///         .find("users", |_: &User| true)
+    ///         .await
+    ///         .expect("Problem searching users.");
+    ///
+    ///     assert_eq!(users[0].id, 1);
+    ///     assert!(db
+    ///         .write_with_headers("users", users, &["id", "email"])
+    ///         .await
+    ///         .is_err());
     /// }
     /// ```
-    pub async fn update<T, P>(
+    pub async fn write_with_headers<T>(
         &self,
         collection: &str,
-        document: T,
-        mut predicate: P,
-    ) -> Result<(), Box<dyn Error>>
+        documents: Vec<T>,
+        headers: &[&str],
+    ) -> Result<(), DbError>
     where
-        T: Serialize + for<'de> Deserialize<'de> + PartialEq + Send + 'static,
-        P: FnMut(&&T) -> bool,
+        T: Serialize + Send + 'static,
     {
-        let mut documents: Vec<T> = self.find(collection, |_| true).await?;
+        let _lock = self.lock(collection).await;
+        self.invalidate(collection);
 
-        documents.retain(|d| !predicate(&d));
-        documents.push(document);
+        let collection = collection.to_string();
+        let headers = StringRecord::from(headers.to_vec());
+        let config = self.config.clone();
+        let result: Result<Result<(), DbError>, JoinError> = task::spawn_blocking(move || {
+            // Serialize the documents in memory first, to learn the column of every field.
+            let mut wrt = WriterBuilder::new().from_writer(Vec::new());
 
-        Ok(self.write(collection, documents).await??)
+            for document in &documents {
+                config.serialize(&mut wrt, document)?;
+            }
+
+            let serialized = wrt.into_inner().map_err(|e| e.into_error())?;
+            let mut rdr = ReaderBuilder::new().from_reader(serialized.as_slice());
+            let records = rdr.records().collect::<Result<Vec<_>, _>>()?;
+            let fields = config.file_headers(rdr.headers()?);
+
+            // Without documents there are no fields to check the headers against.
+            let columns = if documents.is_empty() {
+                Vec::new()
+            } else {
+                headers
+                    .iter()
+                    .map(|header| {
+                        fields
+                            .iter()
+                            .position(|field| field == header)
+                            .ok_or_else(|| {
+                                DbError::Validation(format!("unknown header: {}", header))
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+            };
+
+            Ok(config.replace(&config.collection_path(&collection), |wrt| {
+                if config.has_headers {
+                    wrt.write_record(&headers)?;
+                }
+
+                for record in &records {
+                    wrt.write_record(columns.iter().map(|&column| &record[column]))?;
+                }
+
+                Ok(())
+            })?)
+        })
+        .await;
+
+        result?
     }
 
-    async fn write<T>(
+    /// Compact a collection by rewriting all of its records with the current configuration.
+    ///
+    /// The rewritten file has canonical quoting and terminators, regardless of how the records
+    /// were originally written. Compacting a collection that doesn't exist does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use csv_db::Database;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    /// #   let _ = std::fs::remove_dir_all("data/compact");
+    /// #   std::fs::create_dir_all("data/compact").unwrap();
+    /// #   std::fs::write(
+    /// #       "data/compact/users.csv",
+    /// #       "\"id\",\"first_name\",\"last_name\",\"age\"\r\n\"1\",\"First\",Last,\"20\"\r\n",
+    /// #   )
+    /// #   .unwrap();
+    ///     let db = Database::new("data/compact", None);
+    ///
+    ///     db.compact("users").await.expect("Problem compacting users.");
+    ///
+    ///     assert_eq!(
+    ///         std::fs::read_to_string("data/compact/users.csv").unwrap(),
+    ///         "id,first_name,last_name,age\n1,First,Last,20\n"
+    ///     );
+    /// }
+    /// ```
+    pub async fn compact(&self, collection: &str) -> Result<(), DbError> {
+        let _lock = self.lock(collection).await;
+        self.invalidate(collection);
+
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let result: Result<Result<(), DbError>, JoinError> = task::spawn_blocking(move || {
+            let path = config.collection_path(&collection);
+
+            config.verify_checksum(&collection)?;
+
+            let mut rdr = match config.reader(&collection) {
+                Ok(rdr) => rdr,
+                Err(error) if is_not_found(&error) => return Ok(()),
+                Err(error) => return Err(error.into()),
+            };
+
+            let headers = if config.has_headers {
+                Some(rdr.headers()?.clone())
+            } else {
+                None
+            };
+            let records = rdr.records().collect::<Result<Vec<_>, _>>()?;
+            drop(rdr);
+
+            Ok(config.replace(&path, |wrt| {
+                if let Some(headers) = headers {
+                    wrt.write_record(&config.file_headers(&headers))?;
+                }
+
+                for record in records {
+                    wrt.write_record(&record)?;
+                }
+
+                Ok(())
+            })?)
+        })
+        .await;
+
+        result?
+    }
+
+    /// Begin a transaction, whose writes to any collections are rolled back together unless it is
+    /// committed. See [`Transaction`] for details.
+    pub async fn begin(&self) -> Transaction<'_, PA> {
+        Transaction::new(self)
+    }
+
+    /// Read the documents of a collection on a blocking thread by handing an iterator over them to
+    /// a closure, or return a default value when the collection doesn't exist.
+    async fn read_documents<T, R, F>(
         &self,
         collection: &str,
-        documents: Vec<T>,
-    ) -> Result<Result<(), csv::Error>, JoinError>
+        default: R,
+        read: F,
+    ) -> Result<R, DbError>
     where
-        T: Serialize + Send + 'static,
+        T: for<'de> Deserialize<'de>,
+        R: Send + 'static,
+        F: FnOnce(&mut dyn Iterator<Item = Result<T, csv::Error>>) -> Result<R, csv::Error>
+            + Send
+            + 'static,
     {
         let collection = collection.to_string();
         let config = self.config.clone();
-        let result: Result<Result<(), csv::Error>, JoinError> = task::spawn_blocking(move || {
-            let path = config
-                .path
-                .as_ref()
-                .join(format!("{}.{}", collection, config.extension));
+        let cache = self.cache.clone();
+        let result: Result<Result<R, DbError>, JoinError> =
+            task::spawn_blocking(move || config.read_documents(&cache, &collection, default, read))
+                .await;
 
-            if let Some(parent_path) = path.parent() {
-                std::fs::create_dir_all(parent_path)?
-            }
+        result?
+    }
 
-            let mut wrt = match Writer::from_path(&path) {
-                Ok(wrt) => wrt,
-                Err(error) => match error.kind() {
-                    csv::ErrorKind::Io(_) => match std::fs::File::create(&path) {
-                        Ok(_) => csv::Writer::from_path(&path)?,
-                        Err(_) => return Err(error),
-                    },
-                    _ => return Err(error),
-                },
-            };
+    /// Read a collection on a blocking thread by handing its reader to a closure, or return a
+    /// default value when the collection doesn't exist.
+    async fn read<R, F>(&self, collection: &str, default: R, read: F) -> Result<R, DbError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Reader<Box<dyn Read + Send>>) -> Result<R, csv::Error> + Send + 'static,
+    {
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let result: Result<Result<R, DbError>, JoinError> = task::spawn_blocking(move || {
+            config.verify(&collection)?;
 
-            for document in documents {
-                wrt.serialize(document)?;
+            match config.reader(&collection) {
+                Ok(mut rdr) => Ok(read(&mut rdr)?),
+                Err(error) if is_not_found(&error) => Ok(default),
+                Err(error) => Err(error.into()),
             }
+        })
+        .await;
 
-            Ok(())
+        result?
+    }
+
+    /// Remove the cached records of a collection, if any.
+    fn invalidate(&self, collection: &str) {
+        self.cache
+            .write()
+            .expect("Collection cache poisoned.")
+            .remove(collection);
+    }
+
+    /// Acquire the lock of a collection, which is held until the returned guard is dropped.
+    ///
+    /// Methods that read a collection and then rewrite it hold its lock for their whole duration,
+    /// so that concurrent invocations on the same Database never lose each other's changes.
+    async fn lock(&self, collection: &str) -> OwnedMutexGuard<()> {
+        self.collection_lock(collection).lock_owned().await
+    }
+
+    /// Acquire the lock of a collection from synchronous code, outside of any async runtime.
+    #[cfg(feature = "blocking")]
+    fn lock_blocking(&self, collection: &str) -> OwnedMutexGuard<()> {
+        self.collection_lock(collection).blocking_lock_owned()
+    }
+
+    /// The lock of a collection, shared by every invocation on the same Database.
+    fn collection_lock(&self, collection: &str) -> Arc<AsyncMutex<()>> {
+        self.locks
+            .lock()
+            .expect("Collection locks poisoned.")
+            .entry(collection.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Write documents to a collection, replacing all of its contents.
+    ///
+    /// The documents are serialized into a temporary file next to the collection file, which is
+    /// then renamed over it. If anything fails midway, the temporary file is removed and the
+    /// collection is left unchanged.
+    async fn write<T>(
+        &self,
+        collection: &str,
+        documents: Vec<T>,
+    ) -> Result<Result<(), csv::Error>, JoinError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    {
+        self.invalidate(collection);
+
+        let collection = collection.to_string();
+        let config = self.config.clone();
+        let result: Result<Result<(), csv::Error>, JoinError> = task::spawn_blocking(move || {
+            let skipped = config.skipped_rows::<T>(&collection)?;
+
+            config.write_all(&collection, &documents, &skipped)
         })
         .await;
 
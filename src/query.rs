@@ -0,0 +1,123 @@
+use std::cmp::Ordering;
+
+type Filter<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+type Comparator<T> = Box<dyn Fn(&T, &T) -> Ordering + Send + Sync>;
+
+/// A Query describes a search that can be run on any number of collections with
+/// [`crate::Database::run`].
+///
+/// Documents must match every filter of the query, are then sorted by its key, if any, keeping
+/// documents with equal keys in collection order, and are finally limited to its limit, if any.
+///
+/// # Examples
+///
+/// ```
+/// use csv_db::{Database, Query};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// struct User {
+///     id: usize,
+///     first_name: String,
+///     last_name: String,
+///     age: u32,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+/// #   let _ = std::fs::remove_dir_all("data/query");
+///     let db = Database::new("data/query", None);
+///
+///     for (collection, ages) in [("users", [30, 17, 25, 40]), ("admins", [50, 45, 16, 35])] {
+///         let users = ages
+///             .into_iter()
+///             .enumerate()
+///             .map(|(id, age)| User {
+///                 id,
+///                 first_name: String::from("First"),
+///                 last_name: String::from("Last"),
+///                 age,
+///             })
+///             .collect();
+///
+///         db.insert_many(collection, users)
+///             .await
+///             .expect("Problem inserting users.");
+///     }
+///
+///     let query = Query::new()
+///         .filter(|u: &User| u.age >= 18)
+///         .filter(|u: &User| u.age < 50)
+///         .sort_by(|u: &User| u.age)
+///         .limit(2);
+///
+///     let users = db.run("users", &query).await.expect("Problem running query.");
+///     let admins = db.run("admins", &query).await.expect("Problem running query.");
+///
+///     assert_eq!(users.iter().map(|u| u.age).collect::<Vec<_>>(), vec![25, 30]);
+///     assert_eq!(admins.iter().map(|u| u.age).collect::<Vec<_>>(), vec![35, 45]);
+/// }
+/// ```
+pub struct Query<T> {
+    filters: Vec<Filter<T>>,
+    order: Option<Comparator<T>>,
+    limit: Option<usize>,
+}
+
+impl<T> Query<T> {
+    /// Create a new Query that matches all documents, in collection order.
+    pub fn new() -> Self {
+        Self {
+            filters: Vec::new(),
+            order: None,
+            limit: None,
+        }
+    }
+
+    /// Only match documents for which a predicate is true, in addition to any previous filters.
+    pub fn filter<P>(mut self, predicate: P) -> Self
+    where
+        P: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.filters.push(Box::new(predicate));
+        self
+    }
+
+    /// Sort the matching documents by a key, replacing any previous key.
+    pub fn sort_by<F, K>(mut self, key: F) -> Self
+    where
+        F: Fn(&T) -> K + Send + Sync + 'static,
+        K: Ord,
+    {
+        self.order = Some(Box::new(move |a, b| key(a).cmp(&key(b))));
+        self
+    }
+
+    /// Return at most n of the matching documents, after they are sorted.
+    pub fn limit(mut self, n: usize) -> Self {
+        self.limit = Some(n);
+        self
+    }
+
+    /// Whether a document matches every filter of the query.
+    pub(crate) fn matches(&self, document: &T) -> bool {
+        self.filters.iter().all(|filter| filter(document))
+    }
+
+    /// Sort and limit the documents that matched the query.
+    pub(crate) fn arrange(&self, documents: &mut Vec<T>) {
+        if let Some(order) = &self.order {
+            documents.sort_by(|a, b| order(a, b));
+        }
+
+        if let Some(limit) = self.limit {
+            documents.truncate(limit);
+        }
+    }
+}
+
+impl<T> Default for Query<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
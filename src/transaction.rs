@@ -0,0 +1,307 @@
+use crate::{checksum_path, index_path, remove_if_exists, Database, DbError};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::task::{self, JoinError};
+
+/// A Transaction groups writes to several collections so that they are rolled back together.
+///
+/// Before a collection is first written through the transaction, its file and checksum are copied
+/// to backups next to them. Committing discards the backups, while rolling back, or dropping the transaction
+/// without committing it, restores them. Each write is still applied atomically as it happens,
+/// so the collection locks aren't held between writes and any writes made to the same
+/// collections outside the transaction are lost on rollback.
+///
+/// # Examples
+///
+/// ```
+/// use csv_db::Database;
+/// use serde::{Deserialize, Serialize, Serializer};
+///
+/// fn checked_age<S: Serializer>(age: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+///     if *age > 150 {
+///         return Err(serde::ser::Error::custom("invalid age"));
+///     }
+///
+///     serializer.serialize_u32(*age)
+/// }
+///
+/// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// struct User {
+///     id: usize,
+///     first_name: String,
+///     last_name: String,
+///     #[serde(serialize_with = "checked_age")]
+///     age: u32,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+/// #   let _ = std::fs::remove_dir_all("data/transaction");
+///     let db = Database::new("data/transaction", None);
+///
+///     let user = User {
+///         id: 1,
+///         first_name: String::from("First"),
+///         last_name: String::from("Last"),
+///         age: 20,
+///     };
+///
+///     db.insert("users", user).await.expect("Problem inserting user.");
+///
+///     let mut tx = db.begin().await;
+///
+///     let admin = User {
+///         id: 2,
+///         first_name: String::from("First"),
+///         last_name: String::from("Last"),
+///         age: 30,
+///     };
+///
+///     tx.insert("admins", admin)
+///         .await
+///         .expect("Problem inserting admin.");
+///
+///     let user = User {
+///         id: 3,
+///         first_name: String::from("First"),
+///         last_name: String::from("Last"),
+///         age: 200,
+///     };
+///
+///     assert!(tx.insert("users", user).await.is_err());
+///
+///     tx.rollback().await.expect("Problem rolling back.");
+///
+///     let admins = db
+///         .find("admins", |_: &User| true)
+///         .await
+///         .expect("Problem searching admins.");
+///     let users = db
+///         .find("users", |_: &User| true)
+///         .await
+///         .expect("Problem searching users.");
+///
+///     assert!(admins.is_empty());
+///     assert_eq!(users.len(), 1);
+/// }
+/// ```
+pub struct Transaction<'a, PA>
+where
+    PA: AsRef<Path> + Send + Sync + Clone + 'static,
+{
+    db: &'a Database<PA>,
+    snapshots: Vec<Snapshot>,
+    finished: bool,
+}
+
+/// The state of a collection before it was first written through a transaction.
+struct Snapshot {
+    collection: String,
+    path: PathBuf,
+    existed: bool,
+}
+
+impl Snapshot {
+    /// Put the backup of the collection back in place, or remove its file if it didn't exist.
+    ///
+    /// The checksum of the collection is put back along with it, so that the restored file is
+    /// still verified, while its index is removed, as the restored file no longer matches it.
+    fn restore(&self) -> std::io::Result<()> {
+        let checksum_path = checksum_path(&self.path);
+
+        remove_if_exists(&index_path(&self.path))?;
+
+        if self.existed {
+            std::fs::rename(backup_path(&self.path), &self.path)?;
+        } else {
+            remove_if_exists(&self.path)?;
+        }
+
+        match std::fs::rename(backup_path(&checksum_path), &checksum_path) {
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                remove_if_exists(&checksum_path)
+            }
+            result => result,
+        }
+    }
+
+    /// Remove the backups of the collection and its checksum, if any.
+    fn discard(&self) -> std::io::Result<()> {
+        remove_if_exists(&backup_path(&checksum_path(&self.path)))?;
+
+        if self.existed {
+            std::fs::remove_file(backup_path(&self.path))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The path of the backup file that holds the contents of the file at path during a transaction.
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup_path = path.as_os_str().to_owned();
+    backup_path.push(".bak");
+
+    PathBuf::from(backup_path)
+}
+
+impl<'a, PA> Transaction<'a, PA>
+where
+    PA: AsRef<Path> + Send + Sync + Clone + 'static,
+{
+    pub(crate) fn new(db: &'a Database<PA>) -> Self {
+        Self {
+            db,
+            snapshots: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Insert a new document into a collection as part of the transaction.
+    pub async fn insert<T>(&mut self, collection: &str, document: T) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    {
+        self.snapshot(collection).await?;
+        self.db.insert(collection, document).await
+    }
+
+    /// Insert many new documents into a collection as part of the transaction.
+    pub async fn insert_many<T>(
+        &mut self,
+        collection: &str,
+        documents: Vec<T>,
+    ) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    {
+        self.snapshot(collection).await?;
+        self.db.insert_many(collection, documents).await
+    }
+
+    /// Update a document by filtering with a predicate on a collection as part of the transaction.
+    pub async fn update<T, P>(
+        &mut self,
+        collection: &str,
+        document: T,
+        predicate: P,
+    ) -> Result<(), DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + Send + 'static,
+        P: FnMut(&T) -> bool,
+    {
+        self.snapshot(collection).await?;
+        self.db.update(collection, document, predicate).await
+    }
+
+    /// Delete documents by filtering with a predicate from a collection as part of the
+    /// transaction, returning how many documents were deleted.
+    pub async fn delete<T, P>(&mut self, collection: &str, predicate: P) -> Result<usize, DbError>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + Send + 'static,
+        P: FnMut(&T) -> bool,
+    {
+        self.snapshot(collection).await?;
+        self.db.delete(collection, predicate).await
+    }
+
+    /// Commit the transaction, keeping all of its writes and discarding the backups.
+    pub async fn commit(mut self) -> Result<(), DbError> {
+        self.finish(Snapshot::discard).await
+    }
+
+    /// Roll back the transaction, restoring every collection it wrote to from its backup.
+    pub async fn rollback(mut self) -> Result<(), DbError> {
+        self.finish(Snapshot::restore).await
+    }
+
+    /// Back up a collection before it is first written through the transaction.
+    async fn snapshot(&mut self, collection: &str) -> Result<(), DbError> {
+        if self.snapshots.iter().any(|s| s.collection == collection) {
+            return Ok(());
+        }
+
+        let _lock = self.db.lock(collection).await;
+
+        let collection = collection.to_string();
+        let path = self.db.config.collection_path(&collection);
+        let result: Result<Result<Snapshot, std::io::Error>, JoinError> =
+            task::spawn_blocking(move || {
+                let existed = path.exists();
+                let checksum_path = checksum_path(&path);
+
+                if existed {
+                    std::fs::copy(&path, backup_path(&path))?;
+                }
+
+                if checksum_path.exists() {
+                    std::fs::copy(&checksum_path, backup_path(&checksum_path))?;
+                } else {
+                    remove_if_exists(&backup_path(&checksum_path))?;
+                }
+
+                Ok(Snapshot {
+                    collection,
+                    path,
+                    existed,
+                })
+            })
+            .await;
+
+        self.snapshots.push(result??);
+
+        Ok(())
+    }
+
+    /// Apply an action to every snapshot, holding the lock of each collection while doing so.
+    ///
+    /// The action is applied to the remaining snapshots even if it fails on one of them, so that
+    /// a single failure doesn't leave the other collections unfinished. The first error is
+    /// returned afterwards.
+    async fn finish(
+        &mut self,
+        action: fn(&Snapshot) -> std::io::Result<()>,
+    ) -> Result<(), DbError> {
+        self.finished = true;
+
+        let mut first_error = None;
+
+        for snapshot in std::mem::take(&mut self.snapshots) {
+            let _lock = self.db.lock(&snapshot.collection).await;
+            self.db.invalidate(&snapshot.collection);
+
+            let result: Result<std::io::Result<()>, JoinError> =
+                task::spawn_blocking(move || action(&snapshot)).await;
+
+            let error: DbError = match result {
+                Ok(Ok(())) => continue,
+                Ok(Err(error)) => error.into(),
+                Err(error) => error.into(),
+            };
+
+            first_error.get_or_insert(error);
+        }
+
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<PA> Drop for Transaction<'_, PA>
+where
+    PA: AsRef<Path> + Send + Sync + Clone + 'static,
+{
+    /// Roll back a transaction that was neither committed nor rolled back.
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        for snapshot in &self.snapshots {
+            self.db.invalidate(&snapshot.collection);
+            let _ = snapshot.restore();
+        }
+    }
+}